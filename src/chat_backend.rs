@@ -0,0 +1,358 @@
+// Language models pan Špička can talk through, picked with CHAT_BACKEND, CHAT_MODEL and CHAT_ENDPOINT
+use std::error::Error;
+
+use serde_json::{Value, json};
+use serenity::async_trait;
+
+use crate::CLIENT;
+
+#[derive(Clone)]
+pub enum ChatMessage {
+    User(String),
+    Model(String),
+    // the model asked for these functions, answered by the next ToolResults
+    ToolCalls(Vec<ToolCall>),
+    ToolResults(Vec<ToolResult>),
+}
+
+#[derive(Clone)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    pub args: Value,
+    // the call as the backend sent it, Gemini wants it back untouched
+    pub raw: Value,
+}
+
+#[derive(Clone)]
+pub struct ToolResult {
+    pub id: String,
+    pub name: String,
+    pub result: String,
+}
+
+// A function the model may call, `parameters` is a JSON schema
+pub struct Tool {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub parameters: Value,
+}
+
+pub enum Reply {
+    Text(String),
+    ToolCalls(Vec<ToolCall>),
+}
+
+#[async_trait]
+pub trait ChatBackend: Send + Sync {
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[ChatMessage],
+        tools: &[Tool],
+    ) -> Result<Reply, Box<dyn Error>>;
+}
+
+// Backend from the environment, Gemini unless CHAT_BACKEND says otherwise
+pub fn from_env() -> Result<Box<dyn ChatBackend>, String> {
+    let backend = dotenv::var("CHAT_BACKEND").unwrap_or(String::from("gemini"));
+    let model = dotenv::var("CHAT_MODEL").ok();
+    let endpoint = dotenv::var("CHAT_ENDPOINT").ok();
+    let api_key = dotenv::var("CHAT_API_KEY").ok();
+
+    match backend.as_str() {
+        "gemini" => Ok(Box::new(Gemini {
+            endpoint: endpoint.unwrap_or(String::from(
+                "https://generativelanguage.googleapis.com/v1beta",
+            )),
+            model: model.unwrap_or(String::from("gemini-2.5-flash")),
+            api_key: api_key.ok_or("Failed getting chat API key")?,
+        })),
+        "openai" => Ok(Box::new(OpenAi {
+            endpoint: endpoint.unwrap_or(String::from("https://api.openai.com/v1")),
+            model: model.unwrap_or(String::from("gpt-4o-mini")),
+            // local stand-ins usually don't check it
+            api_key,
+        })),
+        "ollama" => Ok(Box::new(Ollama {
+            endpoint: endpoint.unwrap_or(String::from("http://localhost:11434")),
+            model: model.unwrap_or(String::from("llama3.1")),
+        })),
+        _ => Err(format!("Unknown chat backend {}", backend)),
+    }
+}
+
+pub struct Gemini {
+    endpoint: String,
+    model: String,
+    api_key: String,
+}
+
+#[async_trait]
+impl ChatBackend for Gemini {
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[ChatMessage],
+        tools: &[Tool],
+    ) -> Result<Reply, Box<dyn Error>> {
+        let contents = messages
+            .iter()
+            .map(|message| match message {
+                ChatMessage::User(text) => json!({ "role": "user", "parts": [{ "text": text }] }),
+                ChatMessage::Model(text) => json!({ "role": "model", "parts": [{ "text": text }] }),
+                ChatMessage::ToolCalls(calls) => json!({
+                    "role": "model",
+                    "parts": calls.iter().map(|call| call.raw.clone()).collect::<Vec<_>>()
+                }),
+                ChatMessage::ToolResults(results) => json!({
+                    "role": "user",
+                    "parts": results
+                        .iter()
+                        .map(|result| json!({
+                            "functionResponse": {
+                                "name": result.name,
+                                "response": { "result": result.result }
+                            }
+                        }))
+                        .collect::<Vec<_>>()
+                }),
+            })
+            .collect::<Vec<_>>();
+        let declarations = tools
+            .iter()
+            .map(|tool| {
+                json!({
+                    "name": tool.name,
+                    "description": tool.description,
+                    "parameters": tool.parameters
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let url = format!(
+            "{}/models/{}:generateContent?key={}",
+            self.endpoint.trim_end_matches('/'),
+            self.model,
+            self.api_key
+        );
+        let body = CLIENT
+            .post(url)
+            .json(&json!({
+                "system_instruction": { "parts": [{ "text": system }] },
+                "tools": [{ "function_declarations": declarations }],
+                "contents": contents
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Value>()
+            .await?;
+
+        let parts = body["candidates"][0]["content"]["parts"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        // Gemini has no call ids, the name is enough to pair the answer
+        let calls = parts
+            .iter()
+            .filter_map(|part| {
+                let call = part.get("functionCall")?;
+                let name = call["name"].as_str()?.to_string();
+                Some(ToolCall {
+                    id: name.clone(),
+                    name,
+                    args: call["args"].clone(),
+                    raw: part.clone(),
+                })
+            })
+            .collect::<Vec<_>>();
+        if !calls.is_empty() {
+            return Ok(Reply::ToolCalls(calls));
+        }
+        Ok(Reply::Text(
+            parts
+                .iter()
+                .filter_map(|part| part["text"].as_str())
+                .collect(),
+        ))
+    }
+}
+
+// Anything speaking the OpenAI chat completions API, including local servers
+pub struct OpenAi {
+    endpoint: String,
+    model: String,
+    api_key: Option<String>,
+}
+
+#[async_trait]
+impl ChatBackend for OpenAi {
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[ChatMessage],
+        tools: &[Tool],
+    ) -> Result<Reply, Box<dyn Error>> {
+        let mut request = CLIENT
+            .post(format!(
+                "{}/chat/completions",
+                self.endpoint.trim_end_matches('/')
+            ))
+            .json(&json!({
+                "model": self.model,
+                "messages": openai_messages(system, messages, true),
+                "tools": openai_tools(tools)
+            }));
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let body = request
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Value>()
+            .await?;
+
+        let message = &body["choices"][0]["message"];
+        let calls = message["tool_calls"]
+            .as_array()
+            .map(|calls| {
+                calls
+                    .iter()
+                    .filter_map(|call| {
+                        let function = &call["function"];
+                        Some(ToolCall {
+                            id: call["id"].as_str()?.to_string(),
+                            name: function["name"].as_str()?.to_string(),
+                            // arguments come as a JSON string
+                            args: serde_json::from_str(function["arguments"].as_str()?)
+                                .unwrap_or(Value::Null),
+                            raw: call.clone(),
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        if !calls.is_empty() {
+            return Ok(Reply::ToolCalls(calls));
+        }
+        Ok(Reply::Text(
+            message["content"].as_str().unwrap_or_default().to_string(),
+        ))
+    }
+}
+
+// Local Ollama server, for running without any API keys
+pub struct Ollama {
+    endpoint: String,
+    model: String,
+}
+
+#[async_trait]
+impl ChatBackend for Ollama {
+    async fn complete(
+        &self,
+        system: &str,
+        messages: &[ChatMessage],
+        tools: &[Tool],
+    ) -> Result<Reply, Box<dyn Error>> {
+        let body = CLIENT
+            .post(format!("{}/api/chat", self.endpoint.trim_end_matches('/')))
+            .json(&json!({
+                "model": self.model,
+                "messages": openai_messages(system, messages, false),
+                "tools": openai_tools(tools),
+                "stream": false
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Value>()
+            .await?;
+
+        let message = &body["message"];
+        // Ollama doesn't number its calls, the position does the job
+        let calls = message["tool_calls"]
+            .as_array()
+            .map(|calls| {
+                calls
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(index, call)| {
+                        let function = &call["function"];
+                        Some(ToolCall {
+                            id: index.to_string(),
+                            name: function["name"].as_str()?.to_string(),
+                            args: function["arguments"].clone(),
+                            raw: call.clone(),
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        if !calls.is_empty() {
+            return Ok(Reply::ToolCalls(calls));
+        }
+        Ok(Reply::Text(
+            message["content"].as_str().unwrap_or_default().to_string(),
+        ))
+    }
+}
+
+// Ollama takes the same messages as OpenAI, only with arguments as an object
+fn openai_messages(system: &str, messages: &[ChatMessage], string_arguments: bool) -> Vec<Value> {
+    let mut converted = vec![json!({ "role": "system", "content": system })];
+    for message in messages {
+        match message {
+            ChatMessage::User(text) => converted.push(json!({ "role": "user", "content": text })),
+            ChatMessage::Model(text) => {
+                converted.push(json!({ "role": "assistant", "content": text }))
+            }
+            ChatMessage::ToolCalls(calls) => converted.push(json!({
+                "role": "assistant",
+                "content": "",
+                "tool_calls": calls
+                    .iter()
+                    .map(|call| json!({
+                        "id": call.id,
+                        "type": "function",
+                        "function": {
+                            "name": call.name,
+                            "arguments": if string_arguments {
+                                Value::String(call.args.to_string())
+                            } else {
+                                call.args.clone()
+                            }
+                        }
+                    }))
+                    .collect::<Vec<_>>()
+            })),
+            ChatMessage::ToolResults(results) => converted.extend(results.iter().map(|result| {
+                json!({
+                    "role": "tool",
+                    "tool_call_id": result.id,
+                    "tool_name": result.name,
+                    "content": result.result
+                })
+            })),
+        }
+    }
+    converted
+}
+
+fn openai_tools(tools: &[Tool]) -> Vec<Value> {
+    tools
+        .iter()
+        .map(|tool| {
+            json!({
+                "type": "function",
+                "function": {
+                    "name": tool.name,
+                    "description": tool.description,
+                    "parameters": tool.parameters
+                }
+            })
+        })
+        .collect()
+}
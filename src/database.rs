@@ -1,29 +1,46 @@
+use chrono::NaiveDate;
 use once_cell::sync::Lazy;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use serde_json::from_reader;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs::File,
     io::{BufReader, Write},
     sync::Mutex,
 };
 
+use crate::{lunch_stats::LunchRecord, zmeny::Zmena};
+
+const DATA_PATH: &str = "./database.json";
+const ZMENY_PATH: &str = "./zmeny.json";
+const USERS_PATH: &str = "./users.json";
+const OBEDY_PATH: &str = "./obedy.json";
+const PHOTOS_PATH: &str = "./photos.json";
+
 pub struct Database {
     data: HashMap<String, ServerData>,
+    // every substitution the bot has seen, kept for statistics
+    zmeny: Vec<Zmena>,
+    users: HashMap<String, UserData>,
+    // menus archived daily by the scheduler, for lunch statistics
+    obedy: Vec<LunchRecord>,
+    // server id -> food id -> photo sent by users there, relative to lunch_fetch::PHOTOS_DIR
+    photos: HashMap<String, HashMap<u32, String>>,
 }
 
 impl Database {
-    fn load_from_file(path: &str) -> HashMap<String, ServerData> {
+    fn load_from_file<T: DeserializeOwned + Serialize + Default>(path: &str) -> T {
         // ensure file exists
-        if File::open(path).is_err() {
-            if let Ok(mut file) = File::create(path) {
-                let _ = file.write_all(b"{}");
-            }
+        if File::open(path).is_err()
+            && let Ok(mut file) = File::create(path)
+        {
+            let empty = serde_json::to_string(&T::default()).unwrap_or_default();
+            let _ = file.write_all(empty.as_bytes());
         }
 
         let file = File::open(path);
         if file.is_err() {
-            return HashMap::new();
+            return T::default();
         }
 
         let reader = BufReader::new(file.unwrap());
@@ -38,22 +55,26 @@ impl Database {
                 } else {
                     eprintln!("Created a new empty database file because deserialization failed.");
                 }
-                HashMap::new()
+                T::default()
             }
         }
     }
 
-    fn save_to_file(path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    fn save_to_file<T: Serialize>(
+        path: &str,
+        section: fn(&Database) -> &T,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         let db = DATABASE.lock()?;
         let mut file = File::create(path)?;
-        let json = serde_json::to_string(&db.data)?;
+        let json = serde_json::to_string(section(&db))?;
         file.write_all(json.as_bytes())?;
         Ok(())
     }
 
-    pub fn set_announcement_channel(
+    // Changes settings of one server, creating its entry if needed
+    fn update_server(
         server_id: String,
-        channel_id: Option<String>,
+        update: impl FnOnce(&mut ServerData),
     ) -> Result<(), Box<dyn std::error::Error>> {
         {
             let db = DATABASE.lock();
@@ -61,35 +82,284 @@ impl Database {
                 return Err("Failed to lock database".into());
             }
             let data = &mut db.unwrap().data;
-            if data.get(&server_id).is_none() {
-                data.insert(server_id.clone(), ServerData::empty());
+            update(data.entry(server_id).or_insert_with(ServerData::empty));
+        }
+        Self::save_to_file(DATA_PATH, |db| &db.data)?;
+        Ok(())
+    }
+
+    // Same as update_server, for settings of a single user
+    fn update_user(
+        user_id: String,
+        update: impl FnOnce(&mut UserData),
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        {
+            let mut db = DATABASE.lock()?;
+            update(db.users.entry(user_id).or_default());
+        }
+        Self::save_to_file(USERS_PATH, |db| &db.users)?;
+        Ok(())
+    }
+
+    pub fn set_announcement_channel(
+        server_id: String,
+        channel_id: Option<String>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Self::update_server(server_id, |server| server.announcement_channel = channel_id)
+    }
+
+    pub fn set_canteen(
+        server_id: String,
+        canteen_id: Option<u32>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Self::update_server(server_id, |server| server.canteen_id = canteen_id)
+    }
+
+    pub fn set_lunch_provider(
+        server_id: String,
+        provider: Option<String>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Self::update_server(server_id, |server| server.lunch_provider = provider)
+    }
+
+    pub fn set_lunch_schedule(
+        server_id: String,
+        schedule: Option<(String, String)>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Self::update_server(server_id, |server| match schedule {
+            Some((channel_id, time)) => {
+                server.lunch_channel = Some(channel_id);
+                server.lunch_time = Some(time);
+            }
+            None => {
+                server.lunch_channel = None;
+                server.lunch_time = None;
+            }
+        })
+    }
+
+    pub fn set_lunch_posted(
+        server_id: String,
+        date: NaiveDate,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Self::update_server(server_id, |server| server.lunch_posted = Some(date))
+    }
+
+    // One vote per user and food, voting again overwrites it
+    pub fn set_rating(
+        server_id: String,
+        food_id: u32,
+        user_id: String,
+        stars: u8,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Self::update_server(server_id, |server| {
+            server
+                .ratings
+                .entry(food_id)
+                .or_default()
+                .insert(user_id, stars);
+        })
+    }
+
+    pub fn set_user_canteen(
+        user_id: String,
+        canteen_id: Option<u32>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Self::update_user(user_id, |user| user.canteen_id = canteen_id)
+    }
+
+    pub fn add_food_alert(
+        user_id: String,
+        guild_id: Option<String>,
+        food: String,
+        liked: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Self::update_user(user_id, |user| {
+            user.liked_foods.retain(|liked| *liked != food);
+            user.hated_foods.retain(|hated| *hated != food);
+            if liked {
+                user.liked_foods.push(food);
+            } else {
+                user.hated_foods.push(food);
             }
-            data.get_mut(&server_id).unwrap().announcement_channel = channel_id;
+            if guild_id.is_some() {
+                user.alert_guild = guild_id;
+            }
+        })
+    }
+
+    // Returns whether there was anything to remove
+    pub fn remove_food_alert(
+        user_id: String,
+        food: &str,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut removed = false;
+        Self::update_user(user_id, |user| {
+            let count = user.liked_foods.len() + user.hated_foods.len();
+            user.liked_foods.retain(|liked| liked != food);
+            user.hated_foods.retain(|hated| hated != food);
+            removed = count != user.liked_foods.len() + user.hated_foods.len();
+        })?;
+        Ok(removed)
+    }
+
+    // A newer photo replaces the old one
+    pub fn set_food_photo(
+        server_id: String,
+        food_id: u32,
+        file: String,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        {
+            let mut db = DATABASE.lock()?;
+            db.photos
+                .entry(server_id)
+                .or_default()
+                .insert(food_id, file);
         }
-        Self::save_to_file("./database.json")?;
+        Self::save_to_file(PHOTOS_PATH, |db| &db.photos)?;
         Ok(())
     }
 
+    pub fn get_food_photo(
+        server_id: &str,
+        food_id: u32,
+    ) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let db = DATABASE.lock()?;
+        Ok(db
+            .photos
+            .get(server_id)
+            .and_then(|photos| photos.get(&food_id))
+            .cloned())
+    }
+
+    pub fn get_users() -> Result<HashMap<String, UserData>, Box<dyn std::error::Error>> {
+        let db = DATABASE.lock()?;
+        Ok(db.users.clone())
+    }
+
+    pub fn get_user(user_id: &str) -> Result<UserData, Box<dyn std::error::Error>> {
+        let db = DATABASE.lock()?;
+        Ok(db.users.get(user_id).cloned().unwrap_or_default())
+    }
+
     pub fn get_data() -> Result<HashMap<String, ServerData>, Box<dyn std::error::Error>> {
         let db = DATABASE.lock()?;
         Ok(db.data.clone())
     }
+
+    // Replaces archived changes for every day present in `zmeny`,
+    // so edits made by the school overwrite what we saw earlier
+    pub fn archive_zmeny(zmeny: &[Zmena]) -> Result<(), Box<dyn std::error::Error>> {
+        if zmeny.is_empty() {
+            return Ok(());
+        }
+        {
+            let mut db = DATABASE.lock()?;
+            db.zmeny
+                .retain(|archived| !zmeny.iter().any(|zmena| zmena.date == archived.date));
+            db.zmeny.extend_from_slice(zmeny);
+            db.zmeny.sort_by_key(|zmena| zmena.date);
+        }
+        Self::save_to_file(ZMENY_PATH, |db| &db.zmeny)?;
+        Ok(())
+    }
+
+    pub fn get_zmeny() -> Result<Vec<Zmena>, Box<dyn std::error::Error>> {
+        let db = DATABASE.lock()?;
+        Ok(db.zmeny.clone())
+    }
+
+    // Same as archive_zmeny, days are replaced per canteen,
+    // returns false when the archive already had them and nothing was written
+    pub fn archive_obedy(records: Vec<LunchRecord>) -> Result<bool, Box<dyn std::error::Error>> {
+        if records.is_empty() {
+            return Ok(false);
+        }
+        let days = records
+            .iter()
+            .map(|record| (record.canteen_id, record.date))
+            .collect::<HashSet<_>>();
+        {
+            let mut db = DATABASE.lock()?;
+            let unchanged = db
+                .obedy
+                .iter()
+                .filter(|archived| days.contains(&(archived.canteen_id, archived.date)))
+                .eq(records.iter());
+            if unchanged {
+                return Ok(false);
+            }
+            db.obedy
+                .retain(|archived| !days.contains(&(archived.canteen_id, archived.date)));
+            db.obedy.extend(records);
+            db.obedy.sort_by_key(|record| record.date);
+        }
+        Self::save_to_file(OBEDY_PATH, |db| &db.obedy)?;
+        Ok(true)
+    }
+
+    pub fn get_obedy() -> Result<Vec<LunchRecord>, Box<dyn std::error::Error>> {
+        let db = DATABASE.lock()?;
+        Ok(db.obedy.clone())
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ServerData {
     pub announcement_channel: Option<String>,
+    // Na tácu canteen, falls back to CANTEEN_ID when unset
+    #[serde(default)]
+    pub canteen_id: Option<u32>,
+    // name of the lunch provider, Na tácu when unset
+    #[serde(default)]
+    pub lunch_provider: Option<String>,
+    // daily lunch post, time is "HH:MM" in Prague time
+    #[serde(default)]
+    pub lunch_channel: Option<String>,
+    #[serde(default)]
+    pub lunch_time: Option<String>,
+    // day of the last lunch post, so a restart doesn't post twice
+    #[serde(default)]
+    pub lunch_posted: Option<NaiveDate>,
+    // food id -> user id -> stars, collected with the buttons under lunches
+    #[serde(default)]
+    pub ratings: HashMap<u32, HashMap<String, u8>>,
 }
 impl ServerData {
     pub fn empty() -> Self {
         Self {
             announcement_channel: None,
+            canteen_id: None,
+            lunch_provider: None,
+            lunch_channel: None,
+            lunch_time: None,
+            lunch_posted: None,
+            ratings: HashMap::new(),
         }
     }
 }
 
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct UserData {
+    // overrides the canteen of the server
+    #[serde(default)]
+    pub canteen_id: Option<u32>,
+    // dishes the user gets a DM about the day before
+    #[serde(default)]
+    pub liked_foods: Vec<String>,
+    #[serde(default)]
+    pub hated_foods: Vec<String>,
+    // server the user subscribed from, decides the canteen
+    #[serde(default)]
+    pub alert_guild: Option<String>,
+}
+
 static DATABASE: Lazy<Mutex<Database>> = Lazy::new(|| {
     Mutex::new(Database {
-        data: Database::load_from_file("./database.json"),
+        data: Database::load_from_file(DATA_PATH),
+        zmeny: Database::load_from_file(ZMENY_PATH),
+        users: Database::load_from_file(USERS_PATH),
+        obedy: Database::load_from_file(OBEDY_PATH),
+        photos: Database::load_from_file(PHOTOS_PATH),
     })
 });
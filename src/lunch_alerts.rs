@@ -0,0 +1,74 @@
+// Dishes people like or hate, the scheduler tells them the day before they're served
+use crate::{
+    database::{Database, UserData},
+    lunch_fetch::{Food, food_matches, normalize},
+};
+
+// Liking, hating and listing dishes for the daily alerts
+pub fn odbery_message<'a, I>(
+    user_id: String,
+    guild_id: Option<String>,
+    mut args: I,
+) -> Result<String, String>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let action = args.next();
+    let food = args.collect::<Vec<_>>().join(" ").to_lowercase();
+    if action != Some("odbery") && food.is_empty() {
+        return Err(String::from(
+            "Chybí jídlo, eg. `!ps obedy oblibene svíčková`",
+        ));
+    }
+
+    match action {
+        Some(action @ ("oblibene" | "neoblibene")) => {
+            let liked = action == "oblibene";
+            Database::add_food_alert(user_id, guild_id, food.clone(), liked)
+                .map_err(|why| why.to_string())?;
+            Ok(if liked {
+                format!("Den předem ti napíšu, když bude **{}** 😋", food)
+            } else {
+                format!("Den předem tě varuju, když bude **{}** 🤢", food)
+            })
+        }
+        Some("odebrat") => match Database::remove_food_alert(user_id, &food) {
+            Ok(true) => Ok(format!("**{}** už hlídat nebudu", food)),
+            Ok(false) => Err(format!("**{}** nehlídám 🤔", food)),
+            Err(why) => Err(why.to_string()),
+        },
+        _ => {
+            let user = Database::get_user(&user_id).map_err(|why| why.to_string())?;
+            let list = |foods: &[String]| match foods.is_empty() {
+                true => String::from("nic"),
+                false => foods.join(", "),
+            };
+            Ok(format!(
+                "Oblíbená: {}\nNeoblíbená: {}",
+                list(&user.liked_foods),
+                list(&user.hated_foods)
+            ))
+        }
+    }
+}
+
+// Lines of the alert about tomorrow's dishes, empty when there's nothing to tell
+pub fn alert_lines(user: &UserData, foods: &[Food]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for food in foods {
+        if user
+            .liked_foods
+            .iter()
+            .any(|liked| food_matches(food, &normalize(liked)))
+        {
+            lines.push(format!("Zítra se vaří **{}** 😋", food.name));
+        } else if user
+            .hated_foods
+            .iter()
+            .any(|hated| food_matches(food, &normalize(hated)))
+        {
+            lines.push(format!("Pozor, zítra se vaří **{}** 🤢", food.name));
+        }
+    }
+    lines
+}
@@ -3,108 +3,479 @@
 
 const API_URL: &str = "https://apiv2.natacu.cz/graphql";
 
-use reqwest::blocking::Client;
-use serde_json::{Value, json};
+const OFFERS_QUERY: &str = "query canteenOffersQuery($query: GetOffersInput!) {\n  canteenOffers(query: $query) {\n    id\n    date\n    food {\n      id\n      name\n      averageRating\n      similarNames {\n        alternateName\n        __typename\n      }\n      __typename\n    }\n    __typename\n  }\n}";
+const FOOD_QUERY: &str = "query foodQuery($id: Int!) {\n  food(id: $id) {\n    id\n    name\n    description\n    canteenId\n    averageRating\n    similarNames {\n      alternateName\n      __typename\n    }\n    photos {\n      id\n      s3url\n      __typename\n    }\n    __typename\n  }\n}";
+const CANTEENS_QUERY: &str =
+    "query canteensQuery {\n  canteens {\n    id\n    name\n    city\n    __typename\n  }\n}";
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::Path,
+};
+
+use chrono::{DateTime, Datelike, Days, NaiveDate, TimeZone, Utc, Weekday};
+use chrono_tz::{Europe::Prague, Tz};
+use futures::future::join_all;
+use reqwest::StatusCode;
+use serde::{Deserialize, Deserializer, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+
+use crate::{CLIENT, database::Database, lunch_stats};
 use serenity::{
-    all::{Color, CreateEmbedAuthor, CreateEmbedFooter},
-    builder::CreateEmbed,
+    all::{ButtonStyle, Color, CreateEmbedAuthor, CreateEmbedFooter},
+    async_trait,
+    builder::{
+        CreateActionRow, CreateAttachment, CreateButton, CreateEmbed, CreateSelectMenu,
+        CreateSelectMenuKind, CreateSelectMenuOption,
+    },
 };
 
-pub fn fetch_food(time_delay: i64) -> Value {
-    let canteen_id: u8 = dotenv::var("CANTEEN_ID")
-        .unwrap_or("1".to_string())
-        .parse()
-        .unwrap_or(1);
-    // Multiplied by 1000, because the API takes values in milliseconds, not seconds
-    let timestamp: i64 = (chrono::Utc::now().timestamp() + time_delay) * 1000;
-    // Parse the request body
-    // We do this to modify the query variables
-    let request_body_str = r#"{
-        "operationName": "canteenOffersQuery",
-        "variables": {
-            "query": {
-                "canteenId": 0,
-                "from": "0",
-                "to": "0",
-                "order": "ASC"
+#[derive(Debug)]
+pub enum LunchError {
+    Network(reqwest::Error),
+    Status(StatusCode),
+    GraphQl(Vec<String>),
+    MissingField(&'static str),
+}
+
+impl LunchError {
+    // What the user gets to see instead of the technical details,
+    // `source` is the provider's title, eg. "Na tácu"
+    pub fn friendly(&self, source: &str) -> String {
+        match self {
+            LunchError::Network(_) => format!("{} neodpovídá, zkus to za chvíli 🤔", source),
+            LunchError::Status(status) if status.is_server_error() => {
+                format!("{} má zrovna problémy, zkus to za chvíli 🤔", source)
             }
+            LunchError::Status(_) | LunchError::GraphQl(_) | LunchError::MissingField(_) => {
+                format!("{} poslal něco, čemu nerozumím 🤔", source)
+            }
+        }
+    }
+}
+
+impl fmt::Display for LunchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LunchError::Network(why) => write!(f, "network error: {}", why),
+            LunchError::Status(status) => write!(f, "unexpected HTTP status: {}", status),
+            LunchError::GraphQl(messages) => write!(f, "GraphQL errors: {}", messages.join(", ")),
+            LunchError::MissingField(field) => write!(f, "missing field in response: {}", field),
+        }
+    }
+}
+
+impl std::error::Error for LunchError {}
+
+impl From<reqwest::Error> for LunchError {
+    fn from(why: reqwest::Error) -> Self {
+        LunchError::Network(why)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GraphQlRequest<V> {
+    operation_name: &'static str,
+    variables: V,
+    query: &'static str,
+}
+
+#[derive(Deserialize)]
+struct GraphQlResponse<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphQlError>,
+}
+
+#[derive(Deserialize)]
+struct GraphQlError {
+    message: String,
+}
+
+#[derive(Serialize)]
+struct OffersVariables {
+    query: OffersQuery,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OffersQuery {
+    canteen_id: u32,
+    // The API takes timestamps in milliseconds as strings
+    from: String,
+    to: String,
+    order: &'static str,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OffersData {
+    canteen_offers: Vec<Offer>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Offer {
+    #[serde(deserialize_with = "deserialize_date")]
+    pub date: NaiveDate,
+    pub food: Vec<Food>,
+}
+
+// Dates come as millisecond timestamps, sometimes wrapped in a string
+fn deserialize_date<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDate, D::Error> {
+    let value = Value::deserialize(deserializer)?;
+    let date = match &value {
+        Value::Number(millis) => millis.as_i64().and_then(DateTime::from_timestamp_millis),
+        Value::String(text) => match text.parse::<i64>() {
+            Ok(millis) => DateTime::from_timestamp_millis(millis),
+            Err(_) => DateTime::parse_from_rfc3339(text)
+                .ok()
+                .map(|date| date.to_utc()),
         },
-        "query": "query canteenOffersQuery($query: GetOffersInput!) {\n  canteenOffers(query: $query) {\n    id\n    date\n    food {\n      id\n      name\n      averageRating\n      __typename\n    }\n    __typename\n  }\n}"
-    }"#;
-    let mut request_body: Value = serde_json::from_str(request_body_str).unwrap();
-
-    // Modifying the request body
-    request_body["variables"]["query"]["canteenId"] = json!(canteen_id);
-    request_body["variables"]["query"]["from"] = json!(timestamp.to_string());
-    request_body["variables"]["query"]["to"] = json!(timestamp.to_string());
-
-    // Sending the request
-    let client = Client::new();
-    let food_response = client
+        _ => None,
+    };
+    date.map(|date| date.with_timezone(&Prague).date_naive())
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid offer date: {}", value)))
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Food {
+    pub id: u32,
+    pub name: String,
+    pub average_rating: Option<f64>,
+    #[serde(default)]
+    pub similar_names: Vec<SimilarName>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarName {
+    pub alternate_name: String,
+}
+
+#[derive(Serialize)]
+struct FoodVariables {
+    id: u32,
+}
+
+#[derive(Deserialize)]
+struct FoodData {
+    food: Option<FoodDetail>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FoodDetail {
+    pub id: u32,
+    pub name: String,
+    pub description: Option<String>,
+    pub average_rating: Option<f64>,
+    #[serde(default)]
+    pub similar_names: Vec<SimilarName>,
+    #[serde(default)]
+    pub photos: Vec<Photo>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Photo {
+    pub s3url: String,
+}
+
+#[derive(Deserialize)]
+struct CanteensData {
+    canteens: Vec<Canteen>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Canteen {
+    pub id: u32,
+    pub name: String,
+    pub city: Option<String>,
+}
+
+async fn graphql<V: Serialize, T: DeserializeOwned>(
+    operation_name: &'static str,
+    query: &'static str,
+    variables: V,
+) -> Result<T, LunchError> {
+    let response = CLIENT
         .post(API_URL)
-        .header("Content-Type", "application/json")
-        .body(serde_json::to_string(&request_body).unwrap())
+        .json(&GraphQlRequest {
+            operation_name,
+            variables,
+            query,
+        })
         .send()
-        .unwrap();
+        .await?;
+    if !response.status().is_success() {
+        return Err(LunchError::Status(response.status()));
+    }
 
-    serde_json::from_str(&food_response.text().unwrap()).unwrap()
+    let body = response.json::<GraphQlResponse<T>>().await?;
+    if !body.errors.is_empty() {
+        return Err(LunchError::GraphQl(
+            body.errors.into_iter().map(|error| error.message).collect(),
+        ));
+    }
+    body.data.ok_or(LunchError::MissingField("data"))
 }
 
-pub fn fetch_food_image(id: u32) -> String {
-    // Parse the request body
-    // We do this to modify the query variables
-    let request_body_str = r#"{
-        "operationName":"foodQuery",
-        "variables":{
-            "id":0
+pub async fn fetch_food(canteen_id: u32, date: NaiveDate) -> Result<Vec<Offer>, LunchError> {
+    fetch_offers(
+        canteen_id,
+        day_start(date),
+        day_start(date + Days::new(1)) - 1,
+    )
+    .await
+}
+
+// All offers between two millisecond timestamps in one request
+pub async fn fetch_offers(canteen_id: u32, from: i64, to: i64) -> Result<Vec<Offer>, LunchError> {
+    let data: OffersData = graphql(
+        "canteenOffersQuery",
+        OFFERS_QUERY,
+        OffersVariables {
+            query: OffersQuery {
+                canteen_id,
+                from: from.to_string(),
+                to: to.to_string(),
+                order: "ASC",
+            },
         },
-        "query":"query foodQuery($id: Int!) {\n  food(id: $id) {\n    id\n    name\n    description\n    canteenId\n    averageRating\n    similarNames {\n      alternateName\n      __typename\n    }\n    photos {\n      id\n      s3url\n      __typename\n    }\n    __typename\n  }\n}"
-    }"#;
-    let mut request_body: Value = serde_json::from_str(request_body_str).unwrap();
-    // Modifying the request body
-    request_body["variables"]["id"] = json!(id);
-
-    // Sending the request
-    let client = Client::new();
-    let image_response = client
-        .post(API_URL)
-        .header("Content-Type", "application/json")
-        .body(serde_json::to_string(&request_body).unwrap())
-        .send()
-        .unwrap();
-
-    let serde_object: Value = serde_json::from_str(&image_response.text().unwrap()).unwrap();
-    // JSON formatting magic to return the s3 url
-    let photos = serde_object["data"]["food"]["photos"].as_array().unwrap();
-    let first_photo = match photos.first() {
-        Some(photo) => photo,
-        _ => return "".to_string(),
+    )
+    .await?;
+    Ok(data.canteen_offers)
+}
+
+pub async fn fetch_canteens() -> Result<Vec<Canteen>, LunchError> {
+    let data: CanteensData = graphql("canteensQuery", CANTEENS_QUERY, Value::Null).await?;
+    Ok(data.canteens)
+}
+
+pub async fn fetch_food_detail(id: u32) -> Result<FoodDetail, LunchError> {
+    let data: FoodData = graphql("foodQuery", FOOD_QUERY, FoodVariables { id }).await?;
+    data.food.ok_or(LunchError::MissingField("food"))
+}
+
+pub async fn fetch_food_image(id: u32) -> Result<Option<String>, LunchError> {
+    let food = fetch_food_detail(id).await?;
+    Ok(food.photos.into_iter().next().map(|photo| photo.s3url))
+}
+
+// Where menus come from, Na tácu is the only one so far but schools
+// on strava.cz or with a plain web page can get their own implementation
+#[async_trait]
+pub trait LunchProvider: Send + Sync {
+    // name used in `jidelna zdroj`
+    fn name(&self) -> &'static str;
+
+    // name shown to users, eg. in errors
+    fn title(&self) -> &'static str;
+
+    async fn fetch_day(&self, canteen_id: u32, date: NaiveDate) -> Result<Vec<Offer>, LunchError>;
+
+    // Days from `from` to `to` including both, sources without a range query go day by day
+    async fn fetch_range(
+        &self,
+        canteen_id: u32,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<Offer>, LunchError> {
+        let days = from.iter_days().take_while(|day| *day <= to);
+        let mut offers = Vec::new();
+        for menu in join_all(days.map(|day| self.fetch_day(canteen_id, day))).await {
+            offers.extend(menu?);
+        }
+        Ok(offers)
+    }
+
+    // not every source has photos
+    async fn fetch_image(&self, _food_id: u32) -> Result<Option<String>, LunchError> {
+        Ok(None)
+    }
+
+    // nor descriptions, None leaves the dish without a detail
+    async fn fetch_detail(&self, _food_id: u32) -> Result<Option<FoodDetail>, LunchError> {
+        Ok(None)
+    }
+
+    // Logs a failed fetch and turns it into the message shown to users
+    fn failed(&self, why: LunchError) -> String {
+        println!("Failed fetching lunches from {}: {why}", self.name());
+        why.friendly(self.title())
+    }
+}
+
+pub struct NaTacu;
+
+#[async_trait]
+impl LunchProvider for NaTacu {
+    fn name(&self) -> &'static str {
+        "natacu"
+    }
+
+    fn title(&self) -> &'static str {
+        "Na tácu"
+    }
+
+    async fn fetch_day(&self, canteen_id: u32, date: NaiveDate) -> Result<Vec<Offer>, LunchError> {
+        fetch_food(canteen_id, date).await
+    }
+
+    async fn fetch_range(
+        &self,
+        canteen_id: u32,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<Offer>, LunchError> {
+        fetch_offers(
+            canteen_id,
+            day_start(from),
+            day_start(to + Days::new(1)) - 1,
+        )
+        .await
+    }
+
+    async fn fetch_image(&self, food_id: u32) -> Result<Option<String>, LunchError> {
+        fetch_food_image(food_id).await
+    }
+
+    async fn fetch_detail(&self, food_id: u32) -> Result<Option<FoodDetail>, LunchError> {
+        fetch_food_detail(food_id).await.map(Some)
+    }
+}
+
+static PROVIDERS: &[&dyn LunchProvider] = &[&NaTacu];
+
+pub fn provider(name: &str) -> Option<&'static dyn LunchProvider> {
+    PROVIDERS
+        .iter()
+        .copied()
+        .find(|provider| provider.name() == name)
+}
+
+// Provider chosen in the server config, Na tácu by default
+pub fn guild_provider(guild_id: Option<&str>) -> &'static dyn LunchProvider {
+    guild_id
+        .and_then(|guild_id| {
+            Database::get_data()
+                .ok()?
+                .get(guild_id)?
+                .lunch_provider
+                .clone()
+        })
+        .and_then(|name| provider(&name))
+        .unwrap_or(&NaTacu)
+}
+
+// Dishes of one day in order, each only once even when it is in more offers,
+// the buttons under lunches use the food id and Discord refuses duplicate ids
+pub fn day_foods(offers: Vec<Offer>) -> Vec<Food> {
+    let mut seen = HashSet::new();
+    offers
+        .into_iter()
+        .flat_map(|offer| offer.food)
+        .filter(|food| seen.insert(food.id))
+        .collect()
+}
+
+pub struct CreateObedy {
+    pub embeds: Vec<CreateEmbed>,
+    pub components: Vec<CreateActionRow>,
+    // photos the embeds point to with attachment://
+    pub attachments: Vec<CreateAttachment>,
+}
+
+impl From<CreateEmbed> for CreateObedy {
+    fn from(embed: CreateEmbed) -> Self {
+        Self {
+            embeds: vec![embed],
+            components: Vec::new(),
+            attachments: Vec::new(),
+        }
+    }
+}
+
+// Button ids are "obed:<food id>" to open the detail
+// and "obed:<food id>:<photo>" to page through its photos
+pub const DETAIL_BUTTON: &str = "obed";
+
+// Rating buttons are "hodnotit:<food id>:<stars>:<photo shown in the detail>"
+pub const RATING_BUTTON: &str = "hodnotit";
+
+// Rating menus under the lunches are "hodnoceni:<canteen id>:<date>:<first dish>",
+// their values "<food id>:<stars>"
+pub const RATING_MENU: &str = "hodnoceni";
+
+// Paging buttons are "den:<canteen id>:<date>" and "tyden:<canteen id>:<date>",
+// the canteen is kept so it doesn't change with whoever clicks
+pub const DAY_BUTTON: &str = "den";
+pub const WEEK_BUTTON: &str = "tyden";
+
+// Discord refuses messages with more embeds than this
+const MAX_EMBEDS: usize = 10;
+
+// Average of the votes collected on this server and their count
+pub fn server_rating(guild_id: Option<&str>, food_id: u32) -> Option<(f64, usize)> {
+    let data = Database::get_data().ok()?;
+    average_votes(data.get(guild_id?)?.ratings.get(&food_id)?)
+}
+
+pub fn average_votes(votes: &HashMap<String, u8>) -> Option<(f64, usize)> {
+    if votes.is_empty() {
+        return None;
+    }
+    let sum = votes.values().map(|stars| *stars as f64).sum::<f64>();
+    Some((sum / votes.len() as f64, votes.len()))
+}
+
+// Na tácu rating next to the one from this server
+pub fn rating_text(average_rating: Option<f64>, server: Option<(f64, usize)>) -> String {
+    let mut text = match average_rating {
+        Some(rating) => format!("{:.1}", rating),
+        None => String::from("Bez hodnocení"),
     };
-    first_photo["s3url"].to_string()
+    if let Some((rating, votes)) = server {
+        text.push_str(&format!(" · server {:.1} ({} hlasů)", rating, votes));
+    }
+    text
 }
 
-pub fn get_lunch_embed(days_forward: i64) -> Result<Vec<CreateEmbed>, String> {
-    let lunch_response = fetch_food(days_forward * 86400_i64);
+pub async fn get_lunch_embed(
+    canteen_id: u32,
+    day: NaiveDate,
+    guild_id: Option<&str>,
+) -> Result<CreateObedy, String> {
+    let days_forward = (day - today()).num_days();
+    let provider = guild_provider(guild_id);
+    let offers = provider
+        .fetch_day(canteen_id, day)
+        .await
+        .map_err(|why| provider.failed(why))?;
     // Must handle empty offers (weekends)
-    let offer_array = match lunch_response["data"]["canteenOffers"]
-        .as_array()
-        .unwrap()
-        .first()
-    {
-        Some(offer) => offer["food"].as_array().unwrap(),
-        None => return Err(String::from("Failed getting lunches")),
-    };
+    let mut foods = day_foods(offers);
+    if foods.is_empty() {
+        return Err(String::from("Na tento den nejsou žádné obědy"));
+    }
+    foods.truncate(MAX_EMBEDS);
+
+    // Fetch all the photos at once instead of one after another
+    // A missing photo isn't worth failing the whole command
+    let image_urls = join_all(foods.iter().map(|food| async move {
+        provider.fetch_image(food.id).await.unwrap_or_else(|why| {
+            println!("Failed fetching photo of food {}: {why}", food.id);
+            None
+        })
+    }))
+    .await;
+    let mut attachments = Vec::new();
 
     let mut lunch_counter = 0;
-    let mut embed_vec: Vec<CreateEmbed> = Vec::with_capacity(offer_array.len());
-    for offer in offer_array {
+    let mut embed_vec: Vec<CreateEmbed> = Vec::with_capacity(foods.len());
+    for (food, image_url) in foods.iter().zip(image_urls) {
         lunch_counter += 1;
-        // Formats the image_url, as it can be missing for some foods
-        let image_url = fetch_food_image(offer["id"].as_u64().unwrap_or(0) as u32);
-        let mut trimmed_image_url: Option<String> = None;
-        if !image_url.is_empty() {
-            trimmed_image_url = Some(image_url[1..&image_url.len() - 1].to_string());
-        }
 
         // Formats the date (gotta love the czech language)
         let date: String = match days_forward {
@@ -118,10 +489,19 @@ pub fn get_lunch_embed(days_forward: i64) -> Result<Vec<CreateEmbed>, String> {
             4 => format!("Za {days_forward} dny"),
             _ => format!("Za {days_forward} dnů"),
         };
-        let mut rating = offer["averageRating"].to_string();
-        if rating == "null" {
-            rating = String::from("Bez hodnocení")
-        }
+        let date = format!(
+            "{} · {} {}",
+            date,
+            crate::czech_weekday(day.weekday()),
+            day.format("%-d.%-m.")
+        );
+        let rating = rating_text(food.average_rating, server_rating(guild_id, food.id));
+        let image_url = image_url.or_else(|| {
+            let photo = community_photo(guild_id, food.id)?;
+            let url = format!("attachment://{}", photo.filename);
+            attachments.push(photo);
+            Some(url)
+        });
 
         let embed = CreateEmbed::new()
             .author(
@@ -130,14 +510,674 @@ pub fn get_lunch_embed(days_forward: i64) -> Result<Vec<CreateEmbed>, String> {
                         "https://www.gypce.cz/wp-content/uploads/2013/06/gypce-1.jpg",
                     ),
             )
-            .description(format!("# {}", offer["name"].to_string().replace('"', "")))
-            .thumbnail(trimmed_image_url.unwrap_or(String::from("")))
+            .description(format!("# {}", food.name))
+            .thumbnail(image_url.unwrap_or_default())
             .color(Color::from_rgb(255, 20, 140))
             .footer(CreateEmbedFooter::new(rating).icon_url("https://png.pngtree.com/png-vector/20230222/ourmid/pngtree-shiny-yellow-star-icon-clipart-png-image_6613580.png"));
 
         embed_vec.push(embed);
     }
-    Ok(embed_vec)
+
+    // discord allows 5 buttons per row
+    let buttons = foods
+        .iter()
+        .enumerate()
+        .map(|(index, food)| {
+            CreateButton::new(format!("{}:{}", DETAIL_BUTTON, food.id))
+                .label(format!("🔍 Oběd {}", index + 1))
+                .style(ButtonStyle::Secondary)
+        })
+        .collect::<Vec<_>>();
+    let mut components = buttons
+        .chunks(5)
+        .map(|row| CreateActionRow::Buttons(row.to_vec()))
+        .collect::<Vec<_>>();
+    components.push(day_navigation(canteen_id, day));
+    // votes are kept per server, there is nowhere to store them in DMs
+    if guild_id.is_some() {
+        components.extend(rating_menus(canteen_id, day, &foods));
+    }
+
+    Ok(CreateObedy {
+        embeds: embed_vec,
+        components,
+        attachments,
+    })
+}
+
+// Same as get_lunch_embed, but days without lunches still get the buttons
+// so paging through a holiday doesn't get stuck
+pub async fn day_message(
+    canteen_id: u32,
+    day: NaiveDate,
+    guild_id: Option<&str>,
+) -> Result<CreateObedy, String> {
+    match get_lunch_embed(canteen_id, day, guild_id).await {
+        Ok(obedy) => Ok(obedy),
+        Err(why) => Ok(CreateObedy {
+            embeds: vec![
+                CreateEmbed::new()
+                    .title(format!(
+                        "{} {}",
+                        crate::czech_weekday(day.weekday()),
+                        day.format("%-d.%-m.")
+                    ))
+                    .description(why)
+                    .color(Color::from_rgb(255, 20, 140)),
+            ],
+            components: vec![day_navigation(canteen_id, day)],
+            attachments: Vec::new(),
+        }),
+    }
+}
+
+// Every dish with all five ratings doesn't fit into one menu,
+// so there is one for each five dishes, same as the detail buttons
+fn rating_menus(canteen_id: u32, day: NaiveDate, foods: &[Food]) -> Vec<CreateActionRow> {
+    foods
+        .chunks(5)
+        .enumerate()
+        .map(|(chunk, foods)| {
+            let first = chunk * 5 + 1;
+            let options = foods
+                .iter()
+                .enumerate()
+                .flat_map(|(index, food)| {
+                    (1..=5).map(move |stars| {
+                        CreateSelectMenuOption::new(
+                            format!("Oběd {} · {}", first + index, "⭐".repeat(stars)),
+                            format!("{}:{}", food.id, stars),
+                        )
+                        .description(crate::truncate(&food.name, 100))
+                    })
+                })
+                .collect();
+            CreateActionRow::SelectMenu(
+                CreateSelectMenu::new(
+                    format!("{}:{}:{}:{}", RATING_MENU, canteen_id, day, first),
+                    CreateSelectMenuKind::String { options },
+                )
+                .placeholder(format!(
+                    "⭐ Ohodnotit obědy {}–{}",
+                    first,
+                    first + foods.len() - 1
+                )),
+            )
+        })
+        .collect()
+}
+
+// Weekends are skipped, there is never anything to eat
+fn day_navigation(canteen_id: u32, day: NaiveDate) -> CreateActionRow {
+    CreateActionRow::Buttons(vec![
+        CreateButton::new(format!(
+            "{}:{}:{}",
+            DAY_BUTTON,
+            canteen_id,
+            add_school_days(day, -1)
+        ))
+        .label("◀")
+        .style(ButtonStyle::Secondary),
+        CreateButton::new(format!("{}:{}:{}", WEEK_BUTTON, canteen_id, day))
+            .label("📅 Celý týden")
+            .style(ButtonStyle::Secondary),
+        CreateButton::new(format!(
+            "{}:{}:{}",
+            DAY_BUTTON,
+            canteen_id,
+            add_school_days(day, 1)
+        ))
+        .label("▶")
+        .style(ButtonStyle::Secondary),
+    ])
+}
+
+fn week_navigation(canteen_id: u32, monday: NaiveDate) -> CreateActionRow {
+    // back to today when it is in this week
+    let today = today();
+    let day = if (monday..monday + Days::new(5)).contains(&today) {
+        today
+    } else {
+        monday
+    };
+    CreateActionRow::Buttons(vec![
+        CreateButton::new(format!(
+            "{}:{}:{}",
+            WEEK_BUTTON,
+            canteen_id,
+            monday - Days::new(7)
+        ))
+        .label("◀")
+        .style(ButtonStyle::Secondary),
+        CreateButton::new(format!("{}:{}:{}", DAY_BUTTON, canteen_id, day))
+            .label("🍽️ Po dnech")
+            .style(ButtonStyle::Secondary),
+        CreateButton::new(format!(
+            "{}:{}:{}",
+            WEEK_BUTTON,
+            canteen_id,
+            monday + Days::new(7)
+        ))
+        .label("▶")
+        .style(ButtonStyle::Secondary),
+    ])
+}
+
+// Detail of one dish with a gallery of all its photos
+pub async fn food_detail_message(
+    id: u32,
+    photo: usize,
+    guild_id: Option<&str>,
+) -> Result<CreateObedy, String> {
+    let provider = guild_provider(guild_id);
+    let food = match provider.fetch_detail(id).await {
+        Ok(Some(food)) => food,
+        Ok(None) => return Err(format!("{} o jídlech nic dalšího neví", provider.title())),
+        Err(why) => return Err(provider.failed(why)),
+    };
+    let photo = photo.min(food.photos.len().saturating_sub(1));
+
+    let rating = rating_text(food.average_rating, server_rating(guild_id, food.id));
+    let mut embed = CreateEmbed::new()
+        .title(&food.name)
+        .description(
+            food.description
+                .as_deref()
+                .filter(|description| !description.is_empty())
+                .unwrap_or("Bez popisu"),
+        )
+        .color(Color::from_rgb(255, 20, 140));
+    if !food.similar_names.is_empty() {
+        let names = food
+            .similar_names
+            .iter()
+            .map(|similar| similar.alternate_name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        embed = embed.field("Také známo jako", crate::truncate(&names, 1024), false);
+    }
+    let mut attachments = Vec::new();
+    let footer = match food.photos.get(photo) {
+        Some(current) => {
+            embed = embed.image(&current.s3url);
+            format!("{} · Fotka {}/{}", rating, photo + 1, food.photos.len())
+        }
+        None => match community_photo(guild_id, food.id) {
+            Some(community) => {
+                embed = embed.image(format!("attachment://{}", community.filename));
+                attachments.push(community);
+                format!("{} · Fotka od uživatelů", rating)
+            }
+            None => format!("{} · Bez fotek", rating),
+        },
+    };
+    embed = embed.footer(CreateEmbedFooter::new(footer).icon_url("https://png.pngtree.com/png-vector/20230222/ourmid/pngtree-shiny-yellow-star-icon-clipart-png-image_6613580.png"));
+
+    let mut components = Vec::new();
+    if food.photos.len() > 1 {
+        components.push(CreateActionRow::Buttons(vec![
+            CreateButton::new(format!(
+                "{}:{}:{}",
+                DETAIL_BUTTON,
+                food.id,
+                photo.saturating_sub(1)
+            ))
+            .label("◀")
+            .style(ButtonStyle::Secondary)
+            .disabled(photo == 0),
+            CreateButton::new(format!("{}:{}:{}", DETAIL_BUTTON, food.id, photo + 1))
+                .label("▶")
+                .style(ButtonStyle::Secondary)
+                .disabled(photo + 1 >= food.photos.len()),
+        ]));
+    }
+    // votes are kept per server, there is nowhere to store them in DMs
+    if guild_id.is_some() {
+        components.push(CreateActionRow::Buttons(
+            (1..=5)
+                .map(|stars| {
+                    CreateButton::new(format!("{}:{}:{}:{}", RATING_BUTTON, food.id, stars, photo))
+                        .label(format!("{} ⭐", stars))
+                        .style(ButtonStyle::Primary)
+                })
+                .collect(),
+        ));
+    }
+
+    Ok(CreateObedy {
+        embeds: vec![embed],
+        components,
+        attachments,
+    })
+}
+
+// The canteen is in Prague, so "today" is whatever day it is there,
+// no matter where the bot runs
+pub fn now() -> DateTime<Tz> {
+    Utc::now().with_timezone(&Prague)
+}
+
+pub fn today() -> NaiveDate {
+    now().date_naive()
+}
+
+// Midnight of the given day in Prague as a millisecond timestamp
+fn day_start(date: NaiveDate) -> i64 {
+    Prague
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap_or_default())
+        .earliest()
+        .map(|time| time.timestamp_millis())
+        .unwrap_or_default()
+}
+
+// Monday of the week of `day`, on weekends of the next one
+pub fn week_monday(day: NaiveDate) -> NaiveDate {
+    let monday = day - Days::new(day.weekday().num_days_from_monday() as u64);
+    if day.weekday().num_days_from_monday() >= 5 {
+        monday + Days::new(7)
+    } else {
+        monday
+    }
+}
+
+// Whole school week in one embed, on weekends the next one
+pub async fn get_week_embed(
+    canteen_id: u32,
+    day: NaiveDate,
+    guild_id: Option<&str>,
+) -> Result<CreateObedy, String> {
+    let monday = week_monday(day);
+    let friday = monday + Days::new(4);
+
+    let provider = guild_provider(guild_id);
+    let offers = provider
+        .fetch_range(canteen_id, monday, friday)
+        .await
+        .map_err(|why| provider.failed(why))?;
+
+    let mut embed = CreateEmbed::new()
+        .author(
+            CreateEmbedAuthor::new(format!(
+                "Obědy {} – {}",
+                monday.format("%-d.%-m."),
+                friday.format("%-d.%-m.")
+            ))
+            .icon_url("https://www.gypce.cz/wp-content/uploads/2013/06/gypce-1.jpg"),
+        )
+        .color(Color::from_rgb(255, 20, 140));
+    for date in monday.iter_days().take(5) {
+        let lines = offers
+            .iter()
+            .filter(|offer| offer.date == date)
+            .flat_map(|offer| offer.food.iter())
+            .enumerate()
+            .map(
+                |(index, food)| match (food.average_rating, server_rating(guild_id, food.id)) {
+                    (None, None) => format!("{}. {}", index + 1, food.name),
+                    (rating, server) => format!(
+                        "{}. {} · ⭐ {}",
+                        index + 1,
+                        food.name,
+                        rating_text(rating, server)
+                    ),
+                },
+            )
+            .collect::<Vec<_>>();
+
+        embed = embed.field(
+            format!(
+                "{} {}",
+                crate::czech_weekday(date.weekday()),
+                date.format("%-d.%-m.")
+            ),
+            if lines.is_empty() {
+                String::from("Žádné obědy")
+            } else {
+                crate::truncate(&lines.join("\n"), 1024)
+            },
+            false,
+        );
+    }
+    Ok(CreateObedy {
+        embeds: vec![embed],
+        components: vec![week_navigation(canteen_id, monday)],
+        attachments: Vec::new(),
+    })
+}
+
+// Photos from `obed foto` are kept here, Discord links to them expire
+const PHOTOS_DIR: &str = "./photos";
+
+// Photo sent with `obed foto` on this server, used when the provider has none
+pub fn community_photo(guild_id: Option<&str>, food_id: u32) -> Option<CreateAttachment> {
+    let file = Database::get_food_photo(guild_id?, food_id)
+        .ok()
+        .flatten()?;
+    let data = match std::fs::read(Path::new(PHOTOS_DIR).join(&file)) {
+        Ok(data) => data,
+        Err(why) => {
+            println!("Failed reading photo {}: {}", file, why);
+            return None;
+        }
+    };
+    let extension = Path::new(&file).extension()?.to_str()?;
+    Some(CreateAttachment::bytes(
+        data,
+        format!("foto_{}.{}", food_id, extension),
+    ))
+}
+
+// Stores a photo for dish number `number` of the given day
+pub async fn foto_message(
+    canteen_id: u32,
+    guild_id: Option<&str>,
+    number: Option<&str>,
+    day: Option<&str>,
+    photo_url: Option<String>,
+) -> Result<String, String> {
+    let number = number
+        .and_then(|number| number.parse::<usize>().ok())
+        .filter(|number| *number > 0)
+        .ok_or("Chybí číslo oběda, eg. `!ps obed foto 2`")?;
+    let day = parse_day(day.unwrap_or("0"), today()).ok_or("Nechápu, kterej den chceš 🤔")?;
+    let photo_url = photo_url.ok_or("Přilož k příkazu fotku jídla")?;
+    // photos are kept per server, so nobody else can swap them out
+    let guild_id = guild_id.ok_or("Fotky jde ukládat jen na serveru")?;
+
+    let provider = guild_provider(Some(guild_id));
+    let offers = provider
+        .fetch_day(canteen_id, day)
+        .await
+        .map_err(|why| provider.failed(why))?;
+    let food = day_foods(offers)
+        .into_iter()
+        .nth(number - 1)
+        .ok_or(format!("Oběd {} se ten den nevaří", number))?;
+
+    // the attachment link is signed and stops working after a while, so keep a copy
+    let download = async {
+        CLIENT
+            .get(&photo_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await
+    };
+    let data = match download.await {
+        Ok(data) => data,
+        Err(why) => {
+            println!("Failed downloading photo {}: {}", photo_url, why);
+            return Err(String::from("Fotku se nepodařilo stáhnout"));
+        }
+    };
+    let extension = match image::guess_format(&data) {
+        Ok(image::ImageFormat::Png) => "png",
+        Ok(image::ImageFormat::Jpeg) => "jpg",
+        Ok(image::ImageFormat::WebP) => "webp",
+        _ => return Err(String::from("Tohle není fotka, pošli png, jpg nebo webp")),
+    };
+    let file = format!("{}/{}.{}", guild_id, food.id, extension);
+    let path = Path::new(PHOTOS_DIR).join(&file);
+    let saved = std::fs::create_dir_all(Path::new(PHOTOS_DIR).join(guild_id))
+        .and_then(|_| std::fs::write(&path, &data));
+    if let Err(why) = saved {
+        println!("Failed saving photo {}: {}", path.display(), why);
+        return Err(String::from("Fotku se nepodařilo uložit"));
+    }
+
+    Database::set_food_photo(guild_id.to_string(), food.id, file).map_err(|why| why.to_string())?;
+    Ok(format!("Fotka jídla **{}** uložena, díky!", food.name))
+}
+
+// Menu of one day as plain text, for the chatbot
+pub async fn lunch_text(
+    canteen_id: u32,
+    guild_id: Option<&str>,
+    day: &str,
+) -> Result<String, String> {
+    let day = parse_day(if day.is_empty() { "0" } else { day }, today())
+        .ok_or("Nechápu, kterej den chceš 🤔")?;
+    let provider = guild_provider(guild_id);
+    let offers = provider
+        .fetch_day(canteen_id, day)
+        .await
+        .map_err(|why| provider.failed(why))?;
+
+    let date = format!(
+        "{} {}",
+        crate::czech_weekday(day.weekday()),
+        day.format("%-d.%-m.")
+    );
+    let lines = day_foods(offers)
+        .into_iter()
+        .enumerate()
+        .map(|(index, food)| {
+            let rating = rating_text(food.average_rating, server_rating(guild_id, food.id));
+            format!("{}. {} ({})", index + 1, food.name, rating)
+        })
+        .collect::<Vec<_>>();
+    if lines.is_empty() {
+        return Ok(format!("{} nejsou žádné obědy", date));
+    }
+    Ok(format!("Obědy {}:\n{}", date, lines.join("\n")))
+}
+
+// Entry point for both the prefix and the slash command
+pub async fn obedy_message(
+    args: &str,
+    canteen_id: u32,
+    guild_id: Option<&str>,
+) -> Result<CreateObedy, String> {
+    let mut words = args.split_whitespace();
+    match words.next() {
+        Some("tyden" | "týden") => get_week_embed(canteen_id, today(), guild_id).await,
+        Some("statistiky" | "stats") => {
+            lunch_stats::stats_embed(canteen_id, guild_id, words.next().unwrap_or("pololeti"))
+                .map(CreateObedy::from)
+        }
+        Some("obrazek" | "obrázek") => {
+            crate::menu_card::card_message(canteen_id, guild_id, words.next().unwrap_or("0")).await
+        }
+        Some("hledat") => {
+            let query = words.collect::<Vec<_>>().join(" ");
+            search_embed(canteen_id, guild_id, &query)
+                .await
+                .map(CreateObedy::from)
+        }
+        arg => {
+            let day =
+                parse_day(arg.unwrap_or("0"), today()).ok_or("Nechápu, kterej den chceš 🤔")?;
+            get_lunch_embed(canteen_id, day, guild_id).await
+        }
+    }
+}
+
+// Turns "zítra", "pátek", "2025-10-24" or a number of school days into a date
+pub fn parse_day(arg: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let weekday = match normalize(arg).as_str() {
+        "dnes" => return Some(today),
+        "zitra" => return Some(today + Days::new(1)),
+        "pozitri" => return Some(today + Days::new(2)),
+        "vcera" => return Some(today - Days::new(1)),
+        "po" | "pondeli" => Weekday::Mon,
+        "ut" | "utery" => Weekday::Tue,
+        "st" | "streda" => Weekday::Wed,
+        "ct" | "ctvrtek" => Weekday::Thu,
+        "pa" | "patek" => Weekday::Fri,
+        _ => {
+            return match arg.parse::<i64>() {
+                Ok(days) => Some(add_school_days(today, days)),
+                Err(_) => crate::zmeny::parse_date(arg, today),
+            };
+        }
+    };
+    // the closest such day, today included
+    let ahead = (7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+    Some(today + Days::new(ahead as u64))
+}
+
+// Moves by school days, so 1 on a Friday is Monday
+fn add_school_days(mut date: NaiveDate, days: i64) -> NaiveDate {
+    for _ in 0..days.unsigned_abs() {
+        loop {
+            date = if days > 0 {
+                date + Days::new(1)
+            } else {
+                date - Days::new(1)
+            };
+            if !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+                break;
+            }
+        }
+    }
+    date
+}
+
+// How far ahead we look when searching for a dish
+const SEARCH_WEEKS: u64 = 4;
+
+// Next days a dish is served, matched on its name and alternate names
+pub async fn search_embed(
+    canteen_id: u32,
+    guild_id: Option<&str>,
+    query: &str,
+) -> Result<CreateEmbed, String> {
+    if query.is_empty() {
+        return Err(String::from(
+            "Co mám hledat? eg. `!ps obedy hledat svíčková`",
+        ));
+    }
+    let today = today();
+    let provider = guild_provider(guild_id);
+    let offers = provider
+        .fetch_range(canteen_id, today, today + Days::new(SEARCH_WEEKS * 7 - 1))
+        .await
+        .map_err(|why| provider.failed(why))?;
+
+    let needle = normalize(query);
+    let lines = offers
+        .iter()
+        .flat_map(|offer| offer.food.iter().map(move |food| (offer.date, food)))
+        .filter(|(_, food)| food_matches(food, &needle))
+        .take(10)
+        .map(|(date, food)| {
+            let rating = match food.average_rating {
+                Some(rating) => format!("⭐ {:.1}", rating),
+                None => String::from("Bez hodnocení"),
+            };
+            format!(
+                "**{} {}** · {} · {}",
+                crate::czech_weekday(date.weekday()),
+                date.format("%-d.%-m."),
+                food.name,
+                rating
+            )
+        })
+        .collect::<Vec<_>>();
+
+    Ok(CreateEmbed::new()
+        .title(format!("Kdy bude {}?", crate::truncate(query, 200)))
+        .description(if lines.is_empty() {
+            format!("Následující {} týdny se nevaří 😢", SEARCH_WEEKS)
+        } else {
+            lines.join("\n")
+        })
+        .color(Color::from_rgb(255, 20, 140)))
+}
+
+// Lowercase without diacritics, so "svickova" finds "Svíčková"
+pub fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .map(|ch| match ch {
+            'á' => 'a',
+            'č' => 'c',
+            'ď' => 'd',
+            'é' | 'ě' => 'e',
+            'í' => 'i',
+            'ň' => 'n',
+            'ó' => 'o',
+            'ř' => 'r',
+            'š' => 's',
+            'ť' => 't',
+            'ú' | 'ů' => 'u',
+            'ý' => 'y',
+            'ž' => 'z',
+            _ => ch,
+        })
+        .collect()
+}
+
+// Whether a food matches a search or subscription, needle must be normalized
+pub fn food_matches(food: &Food, needle: &str) -> bool {
+    normalize(&food.name).contains(needle)
+        || food
+            .similar_names
+            .iter()
+            .any(|similar| normalize(&similar.alternate_name).contains(needle))
+}
+
+// Looks up canteens by name or city, so people can find their id
+pub async fn jidelny_message(query: &str) -> Result<CreateEmbed, String> {
+    if query.is_empty() {
+        return Err(String::from(
+            "Napiš název nebo město jídelny, eg. `!ps jidelny Pardubice`",
+        ));
+    }
+    let canteens = fetch_canteens().await.map_err(|why| NaTacu.failed(why))?;
+
+    let query = normalize(query);
+    let lines = canteens
+        .iter()
+        .filter(|canteen| {
+            normalize(&canteen.name).contains(&query)
+                || canteen
+                    .city
+                    .as_ref()
+                    .is_some_and(|city| normalize(city).contains(&query))
+        })
+        .take(25)
+        .map(|canteen| match &canteen.city {
+            Some(city) => format!("`{}` · {} · {}", canteen.id, canteen.name, city),
+            None => format!("`{}` · {}", canteen.id, canteen.name),
+        })
+        .collect::<Vec<_>>();
+
+    Ok(CreateEmbed::new()
+        .title("Nalezené jídelny")
+        .description(if lines.is_empty() {
+            String::from("Žádná jídelna neodpovídá 🤔")
+        } else {
+            crate::truncate(&lines.join("\n"), 4096)
+        })
+        .color(Color::from_rgb(255, 20, 140))
+        .footer(CreateEmbedFooter::new(
+            "Jídelnu serveru nastavíš pomocí !ps jidelna ~id",
+        )))
+}
+
+// The user's own canteen wins over the server's, CANTEEN_ID is the last resort
+pub fn canteen_for(guild_id: Option<String>, user_id: &str) -> u32 {
+    if let Ok(user) = Database::get_user(user_id)
+        && let Some(canteen_id) = user.canteen_id
+    {
+        return canteen_id;
+    }
+    guild_canteen(guild_id)
+}
+
+pub fn guild_canteen(guild_id: Option<String>) -> u32 {
+    if let Some(guild_id) = guild_id
+        && let Ok(data) = Database::get_data()
+        && let Some(canteen_id) = data.get(&guild_id).and_then(|server| server.canteen_id)
+    {
+        return canteen_id;
+    }
+    dotenv::var("CANTEEN_ID")
+        .unwrap_or("1".to_string())
+        .parse()
+        .unwrap_or(1)
 }
 
 use serenity::builder::{CreateCommand, CreateCommandOption};
@@ -148,18 +1188,101 @@ pub fn register() -> CreateCommand {
         .add_option(CreateCommandOption::new(
             CommandOptionType::String,
             "days_forward",
-            "Kolik dní dopředu oběd? 0 - dnes, 1 - zítra atd.",
+            "Počet školních dní, zítra, pátek, 2025-10-24, tyden - celý týden, hledat ~jídlo, statistiky",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "image",
+            "Pošle obědy na den nebo týden jako jeden obrázek",
         ))
 }
 pub fn help_message() -> (&'static str, &'static str) {
     (
-        "`obedy ~dny_dopředu`",
-        "Zašle obědy v gypce jídelně v daný den\n`~dny_dopředu` musí být kladné číslo, 0 - dnes, 1 - zítra atd.",
+        "`obedy ~den`",
+        "Zašle obědy v gypce jídelně v daný den\n`~den` je počet školních dní dopředu (0 - dnes, 1 - příští školní den), **zítra**, **pozítří**, den v týdnu jako **pátek** nebo **út**, nebo datum jako **2025-10-24**\n`obedy tyden` zašle obědy na celý týden, tlačítky ◀ ▶ pod obědy jde listovat po dnech i týdnech\n`obedy obrazek ~den` nebo `obedy obrazek tyden` pošle obědy jako jeden obrázek\nDalší příkazy k obědům vypíše `help obedy`",
     )
 }
 
+// Everything about lunches, split up since one embed field holds only 1024 characters
+pub fn help_fields() -> [(&'static str, &'static str); 4] {
+    [
+        help_message(),
+        (
+            "Detail a fotky",
+            "`obed ~id` ukáže detail jídla s popisem a fotkami, stejně jako tlačítka 🔍 pod obědy\nV detailu nebo nabídkou ⭐ pod obědy jde jídlo na serveru ohodnotit 1 až 5 hvězdami\n`obed foto ~číslo ~den` s přiloženou fotkou ji uloží k obědu s tím číslem pro tento server, když Na tácu žádnou nemá",
+        ),
+        (
+            "Statistiky a hlídání jídel",
+            "`obedy hledat ~jídlo` najde, kdy se příště vaří dané jídlo\n`obedy statistiky ~období` ukáže nejčastější a nejlépe či nejhůř hodnocená jídla a vývoj hodnocení, období je `tyden`, `mesic`, `pololeti` (výchozí) nebo `rok`\n`obedy oblibene ~jídlo` a `obedy neoblibene ~jídlo` ti den předem napíšou, že se jídlo vaří, `obedy odebrat ~jídlo` hlídání zruší a `obedy odbery` je vypíše",
+        ),
+        (
+            "Nastavení jídelny",
+            "`jidelna ~id` nastaví jídelnu serveru (jen pro adminy), `jidelna moje ~id` nastaví tvoji vlastní, `zrusit` místo `~id` nastavení smaže\n`jidelna zdroj ~název` nastaví, odkud se obědy berou, zatím jen **natacu** (jen pro adminy)\n`jidelny ~název` vyhledá jídelny podle názvu nebo města\n`obedy posilat ~čas` bude každý školní den v `~čas` (eg. **7:30**) posílat obědy do tohoto kanálu, `obedy posilat vypnout` to vypne (jen pro adminy)",
+        ),
+    ]
+}
+
 use crate::SlashCommand;
 pub const COMMAND: SlashCommand = SlashCommand {
     register,
     help: help_message,
 };
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn school_days_skip_weekends() {
+        let friday = date(2025, 10, 24);
+        assert_eq!(add_school_days(friday, 0), friday);
+        assert_eq!(add_school_days(friday, 1), date(2025, 10, 27));
+        assert_eq!(add_school_days(friday, 6), date(2025, 11, 3));
+        assert_eq!(add_school_days(date(2025, 10, 25), 1), date(2025, 10, 27));
+        assert_eq!(add_school_days(date(2025, 10, 27), -1), friday);
+        assert_eq!(add_school_days(date(2025, 10, 26), -1), friday);
+        assert_eq!(add_school_days(friday, -5), date(2025, 10, 17));
+    }
+
+    #[test]
+    fn day_foods_skip_repeated_dishes() {
+        let food = |id: u32| Food {
+            id,
+            name: format!("Jídlo {id}"),
+            average_rating: None,
+            similar_names: Vec::new(),
+        };
+        let offer = |foods: Vec<Food>| Offer {
+            date: date(2025, 10, 24),
+            food: foods,
+        };
+        let foods = day_foods(vec![
+            offer(vec![food(1), food(2)]),
+            offer(vec![food(2), food(3)]),
+        ]);
+        assert_eq!(
+            foods.iter().map(|food| food.id).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn parses_days() {
+        let friday = date(2025, 10, 24);
+        let saturday = date(2025, 10, 25);
+        assert_eq!(parse_day("1", friday), Some(date(2025, 10, 27)));
+        assert_eq!(parse_day("-1", date(2025, 10, 27)), Some(friday));
+        assert_eq!(parse_day("dnes", friday), Some(friday));
+        assert_eq!(parse_day("zítra", friday), Some(saturday));
+        assert_eq!(parse_day("pá", friday), Some(friday));
+        assert_eq!(parse_day("pá", saturday), Some(date(2025, 10, 31)));
+        assert_eq!(parse_day("pondělí", saturday), Some(date(2025, 10, 27)));
+        assert_eq!(parse_day("2025-11-03", friday), Some(date(2025, 11, 3)));
+        assert_eq!(parse_day("3.11.", friday), Some(date(2025, 11, 3)));
+        assert_eq!(parse_day("kdykoli", friday), None);
+    }
+}
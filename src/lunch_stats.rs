@@ -0,0 +1,207 @@
+// Archive of served lunches and the statistics over it, `!ps obedy statistiky`
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serenity::{builder::CreateEmbed, model::Color};
+
+use crate::{
+    database::Database,
+    lunch_fetch::{Offer, average_votes, rating_text, today},
+};
+
+// One dish served on one day, archived for statistics
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct LunchRecord {
+    pub canteen_id: u32,
+    pub date: NaiveDate,
+    pub food_id: u32,
+    pub name: String,
+    // Na tácu rating at the time of fetching
+    pub average_rating: Option<f64>,
+}
+
+// Offers as archive records, see scheduler::archive_lunches
+pub fn lunch_records(canteen_id: u32, offers: &[Offer]) -> Vec<LunchRecord> {
+    offers
+        .iter()
+        .flat_map(|offer| {
+            offer.food.iter().map(move |food| LunchRecord {
+                canteen_id,
+                date: offer.date,
+                food_id: food.id,
+                name: food.name.clone(),
+                average_rating: food.average_rating,
+            })
+        })
+        .collect()
+}
+
+// Dishes listed in each section of the statistics
+const STATS_TOP: usize = 5;
+
+// Statistics over the archived menus of one canteen
+pub fn stats_embed(
+    canteen_id: u32,
+    guild_id: Option<&str>,
+    period: &str,
+) -> Result<CreateEmbed, String> {
+    let today = today();
+    let period = crate::zmeny::Period::parse(period, today)
+        .ok_or("Neznámé období, zkus `tyden`, `mesic`, `pololeti` nebo `rok`")?;
+
+    // older archives also have days fetched ahead, those weren't served yet
+    let mut records = Database::get_obedy()
+        .map_err(|why| why.to_string())?
+        .into_iter()
+        .filter(|record| {
+            record.canteen_id == canteen_id && period.contains(record.date) && record.date <= today
+        })
+        .collect::<Vec<_>>();
+    // a dish can be in more offers of the same day
+    records.sort_by_key(|record| (record.food_id, record.date));
+    records.dedup_by_key(|record| (record.food_id, record.date));
+    if records.is_empty() {
+        return Err(format!(
+            "Za {} zatím nemám uložené žádné obědy",
+            period.label
+        ));
+    }
+
+    let data = Database::get_data().map_err(|why| why.to_string())?;
+    let ratings = guild_id
+        .and_then(|guild_id| data.get(guild_id))
+        .map(|server| &server.ratings);
+    let server_rating = |food_id: u32| average_votes(ratings?.get(&food_id)?);
+
+    struct Dish<'a> {
+        name: &'a str,
+        dates: Vec<NaiveDate>,
+        rating: Option<f64>,
+        server: Option<(f64, usize)>,
+    }
+    impl Dish<'_> {
+        // both ratings weigh the same, whichever exists
+        fn score(&self) -> Option<f64> {
+            let scores = [self.rating, self.server.map(|(rating, _)| rating)];
+            let scores = scores.iter().flatten().collect::<Vec<_>>();
+            if scores.is_empty() {
+                return None;
+            }
+            Some(scores.iter().copied().sum::<f64>() / scores.len() as f64)
+        }
+    }
+
+    // records are sorted by food, so every dish is one run
+    let mut dishes = records
+        .chunk_by(|a, b| a.food_id == b.food_id)
+        .map(|runs| Dish {
+            name: &runs[runs.len() - 1].name,
+            dates: runs.iter().map(|record| record.date).collect(),
+            rating: runs.iter().rev().find_map(|record| record.average_rating),
+            server: server_rating(runs[0].food_id),
+        })
+        .collect::<Vec<_>>();
+
+    let days = {
+        let mut days = records.iter().map(|record| record.date).collect::<Vec<_>>();
+        days.sort();
+        days.dedup();
+        days.len()
+    };
+    let repeated = dishes.iter().filter(|dish| dish.dates.len() > 1).count();
+
+    dishes.sort_by_key(|dish| std::cmp::Reverse(dish.dates.len()));
+    let common = dishes
+        .iter()
+        .take(STATS_TOP)
+        .map(|dish| {
+            let mut line = format!(
+                "{} · **{}×**",
+                crate::truncate(dish.name, 60),
+                dish.dates.len()
+            );
+            if let (Some(first), Some(last)) = (dish.dates.first(), dish.dates.last())
+                && dish.dates.len() > 1
+            {
+                let gap = (*last - *first).num_days() / (dish.dates.len() as i64 - 1);
+                line.push_str(&format!(" · každých ~{} dní", gap));
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut rated = dishes
+        .iter()
+        .filter_map(|dish| Some((dish.score()?, dish)))
+        .collect::<Vec<_>>();
+    rated.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    let rated_line = |(_, dish): &(f64, &Dish)| {
+        format!(
+            "{} · {}",
+            crate::truncate(dish.name, 60),
+            rating_text(dish.rating, dish.server)
+        )
+    };
+    let best = rated
+        .iter()
+        .take(STATS_TOP)
+        .map(rated_line)
+        .collect::<Vec<_>>()
+        .join("\n");
+    // with only a few rated dishes the lists would overlap
+    let worst = rated
+        .iter()
+        .rev()
+        .take(rated.len().saturating_sub(STATS_TOP).min(STATS_TOP))
+        .map(rated_line)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    // month -> Na tácu ratings and server averages of everything served
+    let mut months: BTreeMap<(i32, u32), (Vec<f64>, Vec<f64>)> = BTreeMap::new();
+    for record in &records {
+        let month = months
+            .entry((record.date.year(), record.date.month()))
+            .or_default();
+        month.0.extend(record.average_rating);
+        month
+            .1
+            .extend(server_rating(record.food_id).map(|(rating, _)| rating));
+    }
+    let average = |ratings: &[f64]| match ratings.len() {
+        0 => String::from("–"),
+        len => format!("{:.1}", ratings.iter().sum::<f64>() / len as f64),
+    };
+    let trend = months
+        .iter()
+        .map(|((year, month), (na_tacu, server))| {
+            let mut line = format!("{}/{} · {}", month, year, average(na_tacu));
+            if ratings.is_some() {
+                line.push_str(&format!(" · server {}", average(server)));
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut embed = CreateEmbed::new()
+        .color(Color::from_rgb(5, 180, 255))
+        .title(format!("Statistiky obědů za {}", period.label))
+        .description(format!(
+            "**{}** jídel ve **{}** dnech, **{}** různých, **{}** se opakovalo",
+            records.len(),
+            days,
+            dishes.len(),
+            repeated
+        ))
+        .field("Nejčastější jídla", common, false);
+    if !best.is_empty() {
+        embed = embed.field("Nejlépe hodnocená", best, false);
+    }
+    if !worst.is_empty() {
+        embed = embed.field("Nejhůř hodnocená", worst, false);
+    }
+    Ok(embed.field("Hodnocení podle měsíců", trend, false))
+}
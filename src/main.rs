@@ -1,17 +1,29 @@
+mod chat_backend;
 mod chatbot;
 mod database;
+mod lunch_alerts;
 mod lunch_fetch;
+mod lunch_stats;
+mod menu_card;
 mod rozvrh;
+mod scheduler;
 mod zmeny;
 
 use serenity::{
     async_trait,
     builder::{
         CreateCommand, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseFollowup,
-        CreateInteractionResponseMessage, CreateMessage, EditAttachments, EditMessage,
+        CreateInteractionResponseMessage, CreateMessage, EditAttachments, EditInteractionResponse,
+        EditMessage,
     },
     gateway::ActivityData,
-    model::{application::Interaction, channel::Message, prelude::Ready, guild::Guild},
+    model::{
+        application::{ComponentInteraction, ComponentInteractionDataKind, Interaction},
+        channel::Message,
+        guild::Guild,
+        id::GuildId,
+        prelude::Ready,
+    },
     prelude::*,
 };
 
@@ -54,10 +66,17 @@ impl EventHandler for Handler {
 
         // set custom activity
         ctx.set_activity(Some(ActivityData::custom("Kontroluju boty")));
+
+        // start daily jobs
+        scheduler::start(ctx.http.clone());
     }
 
-    // Slash command handler
+    // Slash command and button handler
     async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        if let Interaction::Component(component) = interaction {
+            handle_component(ctx, component).await;
+            return;
+        }
         if let Interaction::Command(command) = interaction {
             let _ = command
                 .create_response(
@@ -81,26 +100,74 @@ impl EventHandler for Handler {
                 }
                 "zmeny" => {
                     let class = get_option_str(&command.data.options, "class").unwrap_or("7B");
+                    let args = match (
+                        get_option_str(&command.data.options, "export"),
+                        get_option_bool(&command.data.options, "image"),
+                    ) {
+                        (Some(format), _) => {
+                            let mut args = vec![class, "export", format];
+                            // same as `zmeny 7B export csv ~od ~do`, `to` alone means nothing
+                            if let Some(from) = get_option_str(&command.data.options, "from") {
+                                args.push(from);
+                                args.extend(get_option_str(&command.data.options, "to"));
+                            }
+                            args
+                        }
+                        (None, Some(true)) => vec![class, "obrazek"],
+                        _ => vec![class],
+                    };
 
-                    let resp = zmeny::zmeny_message(vec![class].into_iter()).await.unwrap();
-                    CreateInteractionResponseFollowup::new()
-                        .add_file(resp.attachment)
-                        .add_embed(resp.embed)
+                    match zmeny::zmeny_message(args.into_iter()).await {
+                        Ok(resp) => {
+                            let followup =
+                                CreateInteractionResponseFollowup::new().add_embed(resp.embed);
+                            match resp.attachment {
+                                Some(attachment) => followup.add_file(attachment),
+                                None => followup,
+                            }
+                        }
+                        Err(why) => CreateInteractionResponseFollowup::new()
+                            .content(format!("Něco se pokazilo: {}", why)),
+                    }
                 }
                 "ai" => {
                     let arg = get_option_str(&command.data.options, "message").unwrap_or("");
 
-                    let resp = chatbot::chat(arg).await.unwrap();
+                    let guild_id = command.guild_id.map(|id| id.to_string());
+                    let canteen_id =
+                        lunch_fetch::canteen_for(guild_id.clone(), &command.user.id.to_string());
+                    let resp = match chatbot::chat(
+                        &command.channel_id.to_string(),
+                        guild_id.as_deref(),
+                        canteen_id,
+                        arg,
+                        None,
+                    )
+                    .await
+                    {
+                        Ok(resp) => resp,
+                        Err(why) => format!("Failed talking with AI: {}", why),
+                    };
                     CreateInteractionResponseFollowup::new().content(resp)
                 }
                 "help" => CreateInteractionResponseFollowup::new().add_embed(help_content()),
                 "obedy" => {
-                    let days_forward = get_option_str(&command.data.options, "days_forward")
-                        .unwrap_or("0")
-                        .parse::<i64>()
-                        .unwrap_or(0);
-                    match lunch_fetch::get_lunch_embed(days_forward) {
-                        Ok(vec) => CreateInteractionResponseFollowup::new().embeds(vec),
+                    let days_forward =
+                        get_option_str(&command.data.options, "days_forward").unwrap_or("0");
+                    let canteen_id = lunch_fetch::canteen_for(
+                        command.guild_id.map(|id| id.to_string()),
+                        &command.user.id.to_string(),
+                    );
+                    let guild_id = command.guild_id.map(|id| id.to_string());
+                    let args = match get_option_bool(&command.data.options, "image") {
+                        Some(true) => format!("obrazek {}", days_forward),
+                        _ => days_forward.to_string(),
+                    };
+                    match lunch_fetch::obedy_message(&args, canteen_id, guild_id.as_deref()).await {
+                        Ok(obedy) => CreateInteractionResponseFollowup::new()
+                            .embeds(obedy.embeds)
+                            .components(obedy.components)
+                            .add_files(obedy.attachments),
                         Err(why) => CreateInteractionResponseFollowup::new()
                             .content(format!("Command failed: {}", why)),
                     }
@@ -118,6 +185,7 @@ impl EventHandler for Handler {
     async fn guild_create(&self, ctx: Context, guild: Guild, is_new: Option<bool>) {
         if let Some(system_channel_id) = guild.system_channel_id {
             // makes sure joined server is new
+            #[allow(clippy::bool_comparison)]
             if is_new.is_none() || is_new.is_some_and(|val| val == false) { return; }
 
             let _ = Database::set_announcement_channel(guild.id.to_string(), Some(system_channel_id.to_string()));
@@ -159,6 +227,7 @@ impl EventHandler for Handler {
             return;
         }
 
+        #[allow(clippy::manual_unwrap_or)]
         let command = match message_iterator.next() {
             Some(command) => command,
             _ => "unknown command",
@@ -210,6 +279,7 @@ where
                 Err(why) => EditMessage::new().content(format!("Něco se pokazilo: {}", why)),
             };
 
+            #[allow(clippy::collapsible_if)]
             if let Ok(mut think_msg_ok) = think_msg {
                 if let Err(why) = think_msg_ok.edit(&meta.context.http, edit_builder).await {
                     println!("failed to edit message: {why:?}");
@@ -218,6 +288,21 @@ where
         }
 
         "zmeny" => {
+            let mut arguments = arguments.peekable();
+            if arguments.peek() == Some(&"archiv") {
+                arguments.next();
+                let embed = match zmeny::archiv_message(arguments) {
+                    Ok(embed) => embed,
+                    Err(why) => return Err(why.to_string()),
+                };
+                let _ = meta
+                    .msg
+                    .channel_id
+                    .send_message(&meta.context.http, CreateMessage::new().embed(embed))
+                    .await;
+                return Ok(());
+            }
+
             let think_msg = meta
                 .msg
                 .channel_id
@@ -229,10 +314,14 @@ where
                 Ok(resp) => EditMessage::new()
                     .content("Bazinga ☝🤓")
                     .embed(resp.embed)
-                    .attachments(EditAttachments::new().add(resp.attachment)),
+                    .attachments(match resp.attachment {
+                        Some(attachment) => EditAttachments::new().add(attachment),
+                        None => EditAttachments::new(),
+                    }),
                 Err(why) => EditMessage::new().content(format!("Něco se pokazilo: {}", why)),
             };
 
+            #[allow(clippy::collapsible_if)]
             if let Ok(mut think_msg_ok) = think_msg {
                 if let Err(why) = think_msg_ok.edit(&meta.context.http, edit_builder).await {
                     println!("failed to edit message: {why:?}");
@@ -286,7 +375,9 @@ where
                     CreateMessage::new().embed(match arguments.next() {
                         Some("rozvrh") => CreateEmbed::new().help_field(rozvrh::help_message()),
                         Some("ai") => CreateEmbed::new().help_field(chatbot::help_message()),
-                        Some("obedy") => CreateEmbed::new().help_field(lunch_fetch::help_message()),
+                        Some("obedy") => lunch_fetch::help_fields()
+                            .into_iter()
+                            .fold(CreateEmbed::new(), |embed, field| embed.help_field(field)),
                         Some("zmeny") => CreateEmbed::new().help_field(zmeny::help_message()),
                         _ => help_content(),
                     }),
@@ -298,13 +389,57 @@ where
         }
 
         "ai" => {
+            let mut arguments = arguments.peekable();
+
+            if arguments.peek() == Some(&"reset") {
+                chatbot::reset(&meta.msg.channel_id.to_string());
+                let _ = meta
+                    .msg
+                    .channel_id
+                    .say(&meta.context.http, "Zapomněl jsem, o čem jsme se bavili 🧹")
+                    .await;
+                return Ok(());
+            }
+
             let think_msg = meta
                 .msg
                 .channel_id
                 .say(&meta.context.http, "Přemejšlim... 🤔")
                 .await;
 
-            let ai_response = match chatbot::chat(&arguments.collect::<Vec<&str>>().join(" ")).await
+            // "what do you think about this?" needs to know what "this" is
+            let replied_to = match &meta.msg.referenced_message {
+                Some(referenced) => {
+                    let name = match meta.msg.guild_id {
+                        Some(guild_id) => {
+                            referenced
+                                .author
+                                .nick_in(&meta.context.http, guild_id)
+                                .await
+                        }
+                        None => None,
+                    };
+                    Some((
+                        name.unwrap_or_else(|| referenced.author.display_name().to_string()),
+                        chatbot::message_text(referenced),
+                    ))
+                }
+                None => None,
+            };
+
+            let guild_id = meta.msg.guild_id.map(|id| id.to_string());
+            let canteen_id =
+                lunch_fetch::canteen_for(guild_id.clone(), &meta.msg.author.id.to_string());
+            let ai_response = match chatbot::chat(
+                &meta.msg.channel_id.to_string(),
+                guild_id.as_deref(),
+                canteen_id,
+                &arguments.collect::<Vec<&str>>().join(" "),
+                replied_to
+                    .as_ref()
+                    .map(|(name, text)| (name.as_str(), text.as_str())),
+            )
+            .await
             {
                 Ok(resp) => resp,
                 Err(why) => format!("Failed talking with AI: {}", why),
@@ -312,6 +447,7 @@ where
 
             let edit_builder = EditMessage::new().content(ai_response);
 
+            #[allow(clippy::collapsible_if)]
             if let Ok(mut think_msg_ok) = think_msg {
                 if let Err(why) = think_msg_ok.edit(&meta.context.http, edit_builder).await {
                     println!("failed to edit message: {why:?}");
@@ -347,53 +483,124 @@ where
         }
 
         "obedy" => {
-            let days_forward = arguments.next().unwrap_or("0").parse::<i64>().unwrap_or(0);
+            let mut arguments = arguments.peekable();
 
-            let embed_vec = match lunch_fetch::get_lunch_embed(days_forward) {
-                Ok(vec) => vec,
-                Err(e) => return Err(e.to_string()),
+            if arguments.peek() == Some(&"posilat") {
+                arguments.next();
+                let guild_id = check_manage_permissions(&meta).await?;
+                let schedule = match arguments.next() {
+                    Some("vypnout") => None,
+                    Some(time) => match chrono::NaiveTime::parse_from_str(time, "%H:%M") {
+                        Ok(time) => Some((
+                            meta.msg.channel_id.to_string(),
+                            time.format("%H:%M").to_string(),
+                        )),
+                        Err(_) => return Err(format!("`{}` není čas, eg. `7:30`", time)),
+                    },
+                    None => return Err(String::from("Chybí čas, eg. `!ps obedy posilat 7:30`")),
+                };
+                if let Err(why) = Database::set_lunch_schedule(guild_id.to_string(), schedule) {
+                    return Err(why.to_string());
+                }
+                let _ = meta
+                    .msg
+                    .channel_id
+                    .say(&meta.context.http, "Obědy nastaveny!")
+                    .await;
+                return Ok(());
+            }
+
+            let canteen_id = lunch_fetch::canteen_for(
+                meta.msg.guild_id.map(|id| id.to_string()),
+                &meta.msg.author.id.to_string(),
+            );
+
+            if let Some(&("oblibene" | "neoblibene" | "odebrat" | "odbery")) = arguments.peek() {
+                let response = lunch_alerts::odbery_message(
+                    meta.msg.author.id.to_string(),
+                    meta.msg.guild_id.map(|id| id.to_string()),
+                    arguments,
+                )?;
+                let _ = meta.msg.channel_id.say(&meta.context.http, response).await;
+                return Ok(());
+            }
+
+            let args = arguments.collect::<Vec<&str>>().join(" ");
+            let guild_id = meta.msg.guild_id.map(|id| id.to_string());
+            let obedy =
+                match lunch_fetch::obedy_message(&args, canteen_id, guild_id.as_deref()).await {
+                    Ok(obedy) => obedy,
+                    Err(e) => return Err(e.to_string()),
+                };
+
+            let _ = meta
+                .msg
+                .channel_id
+                .send_message(
+                    &meta.context,
+                    CreateMessage::new()
+                        .embeds(obedy.embeds)
+                        .components(obedy.components)
+                        .add_files(obedy.attachments),
+                )
+                .await;
+        }
+
+        "obed" => {
+            let mut arguments = arguments.peekable();
+
+            if arguments.peek() == Some(&"foto") {
+                arguments.next();
+                let guild_id = meta.msg.guild_id.map(|id| id.to_string());
+                let canteen_id =
+                    lunch_fetch::canteen_for(guild_id.clone(), &meta.msg.author.id.to_string());
+                let photo_url = meta
+                    .msg
+                    .attachments
+                    .iter()
+                    .find(|attachment| {
+                        attachment
+                            .content_type
+                            .as_deref()
+                            .is_some_and(|content_type| content_type.starts_with("image/"))
+                    })
+                    .map(|attachment| attachment.url.clone());
+
+                let reply = lunch_fetch::foto_message(
+                    canteen_id,
+                    guild_id.as_deref(),
+                    arguments.next(),
+                    arguments.next(),
+                    photo_url,
+                )
+                .await?;
+                let _ = meta.msg.channel_id.say(&meta.context.http, reply).await;
+                return Ok(());
+            }
+
+            let id = match arguments.next().map(|id| id.parse::<u32>()) {
+                Some(Ok(id)) => id,
+                _ => return Err(String::from("Chybí číslo jídla, eg. `!ps obed 1234`")),
             };
+            let guild_id = meta.msg.guild_id.map(|id| id.to_string());
+            let detail = lunch_fetch::food_detail_message(id, 0, guild_id.as_deref()).await?;
 
             let _ = meta
                 .msg
                 .channel_id
-                .send_message(&meta.context, CreateMessage::new().embeds(embed_vec))
+                .send_message(
+                    &meta.context,
+                    CreateMessage::new()
+                        .embeds(detail.embeds)
+                        .components(detail.components)
+                        .add_files(detail.attachments),
+                )
                 .await;
         }
 
         "announcements" => {
             // first check permissions
-            let guild_id = match meta.msg.guild_id {
-                Some(gid) => gid,
-                None => return Err(String::from("Failed getting guild id")),
-            };
-            let channel = match meta.msg.channel_id.to_channel(&meta.context.http).await {
-                Ok(ch) => match ch.guild() {
-                    Some(gch) => gch,
-                    None => return Err(String::from("Failed getting guild channel")),
-                },
-                Err(why) => return Err(format!("Failed getting channel: {}", why)),
-            };
-            let member = match guild_id
-                .member(&meta.context.http, meta.msg.author.id)
-                .await
-            {
-                Ok(member) => member,
-                Err(why) => return Err(format!("Failed getting member object: {}", why)),
-            };
-            match guild_id.to_guild_cached(&meta.context.cache) {
-                Some(guild) => {
-                    if !guild
-                        .user_permissions_in(&channel, &member)
-                        .manage_channels()
-                    {
-                        return Err(String::from("Insufficient permissions"));
-                    }
-                }
-                None => {
-                    return Err(String::from("Failed checking permissions"));
-                }
-            }
+            let guild_id = check_manage_permissions(&meta).await?;
 
             let mut to_set = Some(meta.msg.channel_id.to_string());
             if arguments.next() == Some("disable") {
@@ -410,6 +617,69 @@ where
                 .await;
         }
 
+        "jidelny" => {
+            let query = arguments.collect::<Vec<&str>>().join(" ");
+            let embed = lunch_fetch::jidelny_message(&query).await?;
+            let _ = meta
+                .msg
+                .channel_id
+                .send_message(&meta.context.http, CreateMessage::new().embed(embed))
+                .await;
+        }
+
+        "jidelna" => {
+            let mut arguments = arguments.peekable();
+
+            if arguments.peek() == Some(&"zdroj") {
+                arguments.next();
+                let guild_id = check_manage_permissions(&meta).await?;
+                let provider = match arguments.next() {
+                    Some("zrusit") => None,
+                    Some(name) => match lunch_fetch::provider(name) {
+                        Some(provider) => Some(provider.name().to_string()),
+                        None => return Err(format!("Neznámý zdroj obědů `{}`", name)),
+                    },
+                    None => {
+                        return Err(String::from("Chybí zdroj, eg. `!ps jidelna zdroj natacu`"));
+                    }
+                };
+                if let Err(why) = Database::set_lunch_provider(guild_id.to_string(), provider) {
+                    return Err(why.to_string());
+                }
+                let _ = meta
+                    .msg
+                    .channel_id
+                    .say(&meta.context.http, "Zdroj obědů nastaven!")
+                    .await;
+                return Ok(());
+            }
+
+            let (owner, canteen_arg) = match arguments.next() {
+                Some("moje") => (None, arguments.next()),
+                arg => (Some(check_manage_permissions(&meta).await?), arg),
+            };
+            let canteen_id = match canteen_arg {
+                Some("zrusit") => None,
+                Some(id) => match id.parse::<u32>() {
+                    Ok(id) => Some(id),
+                    Err(_) => return Err(format!("`{}` není číslo jídelny", id)),
+                },
+                None => return Err(String::from("Chybí číslo jídelny, eg. `!ps jidelna 1`")),
+            };
+
+            if let Err(why) = match owner {
+                Some(guild_id) => Database::set_canteen(guild_id.to_string(), canteen_id),
+                None => Database::set_user_canteen(meta.msg.author.id.to_string(), canteen_id),
+            } {
+                return Err(why.to_string());
+            }
+            let _ = meta
+                .msg
+                .channel_id
+                .say(&meta.context.http, "Jídelna nastavena!")
+                .await;
+        }
+
         "announce" => {
             // first check permissions
             if meta.msg.author.id.to_string() != "416295343198568458" {
@@ -452,11 +722,241 @@ where
     Ok(())
 }
 
+// Button clicks
+async fn handle_component(ctx: Context, component: ComponentInteraction) {
+    let guild_id = component.guild_id.map(|id| id.to_string());
+    let mut parts = component.data.custom_id.split(':');
+    let kind = parts.next();
+    // opening a detail only shows it to whoever clicked, paging it has the photo in the id
+    let opens_detail = kind == Some(lunch_fetch::DETAIL_BUTTON)
+        && component.data.custom_id.split(':').nth(2).is_none();
+
+    // fetching often takes longer than the 3 seconds discord waits for an answer,
+    // so the click is acknowledged first and the message edited once we have it
+    let acknowledgement = match kind {
+        Some(lunch_fetch::DETAIL_BUTTON) if opens_detail => CreateInteractionResponse::Defer(
+            CreateInteractionResponseMessage::new().ephemeral(true),
+        ),
+        Some(
+            lunch_fetch::RATING_BUTTON
+            | lunch_fetch::RATING_MENU
+            | lunch_fetch::DAY_BUTTON
+            | lunch_fetch::WEEK_BUTTON
+            | lunch_fetch::DETAIL_BUTTON,
+        ) => CreateInteractionResponse::Acknowledge,
+        _ => return,
+    };
+    if let Err(why) = component.create_response(&ctx.http, acknowledgement).await {
+        println!("Failed responding to button: {why:?}");
+        return;
+    }
+
+    let obedy = match kind {
+        Some(lunch_fetch::RATING_BUTTON) => {
+            let mut numbers = parts.map(|part| part.parse::<u32>().unwrap_or(0));
+            let (food_id, stars, photo) = (
+                numbers.next().unwrap_or(0),
+                numbers.next().unwrap_or(0).clamp(1, 5),
+                numbers.next().unwrap_or(0),
+            );
+
+            let saved = match &guild_id {
+                Some(guild_id) => Database::set_rating(
+                    guild_id.clone(),
+                    food_id,
+                    component.user.id.to_string(),
+                    stars as u8,
+                )
+                .map_err(|why| why.to_string()),
+                None => Err(String::from("Hodnotit jde jen na serveru")),
+            };
+            // the detail gets redrawn with the new server average
+            match saved {
+                Ok(()) => {
+                    lunch_fetch::food_detail_message(food_id, photo as usize, guild_id.as_deref())
+                        .await
+                }
+                Err(why) => Err(why),
+            }
+        }
+        Some(lunch_fetch::RATING_MENU) => {
+            let canteen_id = parts.next().and_then(|id| id.parse::<u32>().ok());
+            let day = parts
+                .next()
+                .and_then(|day| day.parse::<chrono::NaiveDate>().ok());
+            let vote = match &component.data.kind {
+                ComponentInteractionDataKind::StringSelect { values } => values
+                    .first()
+                    .and_then(|value| value.split_once(':'))
+                    .and_then(|(food_id, stars)| {
+                        Some((food_id.parse::<u32>().ok()?, stars.parse::<u8>().ok()?))
+                    }),
+                _ => None,
+            };
+
+            let saved = match (&guild_id, vote) {
+                (Some(guild_id), Some((food_id, stars))) => Database::set_rating(
+                    guild_id.clone(),
+                    food_id,
+                    component.user.id.to_string(),
+                    stars.clamp(1, 5),
+                )
+                .map_err(|why| why.to_string()),
+                (None, _) => Err(String::from("Hodnotit jde jen na serveru")),
+                _ => Err(String::from("Neplatné hodnocení")),
+            };
+            // the lunches get redrawn with the new server average,
+            // a failed fetch leaves them as they were and only tells the voter
+            match (saved, canteen_id, day) {
+                (Ok(()), Some(canteen_id), Some(day)) => {
+                    lunch_fetch::get_lunch_embed(canteen_id, day, guild_id.as_deref())
+                        .await
+                        .map_err(|why| format!("hlas je uložený, ale obědy nejdou načíst: {}", why))
+                }
+                (Ok(()), _, _) => Err(String::from("Neplatné tlačítko")),
+                (Err(why), _, _) => Err(why),
+            }
+        }
+        Some(kind @ (lunch_fetch::DAY_BUTTON | lunch_fetch::WEEK_BUTTON)) => {
+            let canteen_id = parts.next().and_then(|id| id.parse::<u32>().ok());
+            let day = parts
+                .next()
+                .and_then(|day| day.parse::<chrono::NaiveDate>().ok());
+
+            // paging edits the lunches in place for everyone
+            match (canteen_id, day) {
+                (Some(canteen_id), Some(day)) if kind == lunch_fetch::DAY_BUTTON => {
+                    lunch_fetch::day_message(canteen_id, day, guild_id.as_deref()).await
+                }
+                (Some(canteen_id), Some(day)) => {
+                    lunch_fetch::get_week_embed(canteen_id, day, guild_id.as_deref()).await
+                }
+                _ => Err(String::from("Neplatné tlačítko")),
+            }
+        }
+        _ => {
+            let id = parts
+                .next()
+                .and_then(|id| id.parse::<u32>().ok())
+                .unwrap_or(0);
+            let photo = parts
+                .next()
+                .and_then(|photo| photo.parse::<usize>().ok())
+                .unwrap_or(0);
+            lunch_fetch::food_detail_message(id, photo, guild_id.as_deref()).await
+        }
+    };
+
+    let sent = match obedy {
+        Ok(obedy) => component
+            .edit_response(
+                &ctx.http,
+                EditInteractionResponse::new()
+                    .embeds(obedy.embeds)
+                    .components(obedy.components)
+                    // replaces the photos of the previous page
+                    .attachments(
+                        obedy
+                            .attachments
+                            .into_iter()
+                            .fold(EditAttachments::new(), EditAttachments::add),
+                    ),
+            )
+            .await
+            .map(|_| ()),
+        // the deferred detail is empty, it can hold the error itself
+        Err(why) if opens_detail => component
+            .edit_response(
+                &ctx.http,
+                EditInteractionResponse::new().content(format!("Něco se pokazilo: {}", why)),
+            )
+            .await
+            .map(|_| ()),
+        // don't overwrite the lunches with an error
+        Err(why) => component
+            .create_followup(
+                &ctx.http,
+                CreateInteractionResponseFollowup::new()
+                    .content(format!("Něco se pokazilo: {}", why))
+                    .ephemeral(true),
+            )
+            .await
+            .map(|_| ()),
+    };
+    if let Err(why) = sent {
+        println!("Failed editing button response: {why:?}");
+    }
+}
+
+// Makes sure the author can manage the channel, returns the guild it's in
+async fn check_manage_permissions(meta: &CommandMeta) -> Result<GuildId, String> {
+    let guild_id = match meta.msg.guild_id {
+        Some(gid) => gid,
+        None => return Err(String::from("Failed getting guild id")),
+    };
+    let channel = match meta.msg.channel_id.to_channel(&meta.context.http).await {
+        Ok(ch) => match ch.guild() {
+            Some(gch) => gch,
+            None => return Err(String::from("Failed getting guild channel")),
+        },
+        Err(why) => return Err(format!("Failed getting channel: {}", why)),
+    };
+    let member = match guild_id
+        .member(&meta.context.http, meta.msg.author.id)
+        .await
+    {
+        Ok(member) => member,
+        Err(why) => return Err(format!("Failed getting member object: {}", why)),
+    };
+    match guild_id.to_guild_cached(&meta.context.cache) {
+        Some(guild) => {
+            if !guild
+                .user_permissions_in(&channel, &member)
+                .manage_channels()
+            {
+                return Err(String::from("Insufficient permissions"));
+            }
+        }
+        None => {
+            return Err(String::from("Failed checking permissions"));
+        }
+    }
+    Ok(guild_id)
+}
+
 // helper function to get slash command parameters
 use serenity::model::application::CommandDataOption;
 fn get_option_str<'a>(options: &'a [CommandDataOption], name: &str) -> Option<&'a str> {
     options.iter().find(|opt| opt.name == name)?.value.as_str()
 }
+fn get_option_bool(options: &[CommandDataOption], name: &str) -> Option<bool> {
+    options.iter().find(|opt| opt.name == name)?.value.as_bool()
+}
+
+// helper functions shared by the modules
+// one connection pool for every http request the modules make
+pub static CLIENT: once_cell::sync::Lazy<reqwest::Client> =
+    once_cell::sync::Lazy::new(reqwest::Client::new);
+pub fn czech_weekday(weekday: chrono::Weekday) -> &'static str {
+    match weekday {
+        chrono::Weekday::Mon => "Pondělí",
+        chrono::Weekday::Tue => "Úterý",
+        chrono::Weekday::Wed => "Středa",
+        chrono::Weekday::Thu => "Čtvrtek",
+        chrono::Weekday::Fri => "Pátek",
+        chrono::Weekday::Sat => "Sobota",
+        chrono::Weekday::Sun => "Neděle",
+    }
+}
+// cuts text to fit discord limits (counted in characters)
+pub fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut truncated = text.chars().take(max_chars - 1).collect::<String>();
+    truncated.push('…');
+    truncated
+}
 
 fn help_content() -> CreateEmbed {
     let mut embed = CreateEmbed::new()
@@ -507,3 +1007,21 @@ async fn main() {
     println!("Launching pan-spicka v0.1.4");
     let _ = client.start().await;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Discord rejects embeds with longer fields
+    #[test]
+    fn help_fits_into_fields() {
+        let fields = SLASH_COMMANDS
+            .iter()
+            .map(|command| (command.help)())
+            .chain(lunch_fetch::help_fields());
+        for (name, value) in fields {
+            assert!(name.chars().count() <= 256, "{name}");
+            assert!(value.chars().count() <= 1024, "{name}");
+        }
+    }
+}
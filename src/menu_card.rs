@@ -0,0 +1,339 @@
+// Draws the menu of a day or a week into one PNG, so it doesn't flood the channel with embeds
+use std::io::Cursor;
+
+use ab_glyph::{FontArc, PxScale};
+use chrono::{Datelike, Days, NaiveDate};
+use futures::future::join_all;
+use image::{
+    DynamicImage, ImageFormat, Rgba, RgbaImage,
+    imageops::{self, FilterType},
+};
+use imageproc::{
+    drawing::{draw_filled_rect_mut, draw_text_mut, text_size},
+    rect::Rect,
+};
+use serenity::builder::CreateAttachment;
+
+use crate::lunch_fetch::{self, CreateObedy};
+
+// Built in so the card works without any fonts installed, CARD_FONT and CARD_FONT_BOLD override it
+const DEFAULT_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans.ttf");
+const DEFAULT_BOLD_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans-Bold.ttf");
+
+const WIDTH: u32 = 800;
+const PADDING: i32 = 20;
+const HEADER_HEIGHT: u32 = 70;
+const DAY_HEIGHT: u32 = 44;
+const PHOTO_SIZE: u32 = 80;
+const ROW_HEIGHT: u32 = PHOTO_SIZE + 20;
+
+const PINK: Rgba<u8> = Rgba([255, 20, 140, 255]);
+const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const BACKGROUND: Rgba<u8> = Rgba([30, 31, 34, 255]);
+const TEXT: Rgba<u8> = Rgba([242, 243, 245, 255]);
+const MUTED: Rgba<u8> = Rgba([181, 186, 193, 255]);
+const PLACEHOLDER: Rgba<u8> = Rgba([56, 58, 64, 255]);
+
+struct Dish {
+    name: String,
+    rating: String,
+    photo: Option<DynamicImage>,
+}
+
+struct Day {
+    title: String,
+    dishes: Vec<Dish>,
+}
+
+struct Fonts {
+    regular: FontArc,
+    bold: FontArc,
+}
+
+impl Fonts {
+    fn load() -> Result<Self, String> {
+        let load = |var: &str, default: &'static [u8]| {
+            let Ok(path) = dotenv::var(var) else {
+                return FontArc::try_from_slice(default).map_err(|why| why.to_string());
+            };
+            let data = std::fs::read(&path).map_err(|why| {
+                println!("Failed to read font {}: {}", path, why);
+                String::from("Chybí font pro obrázek obědů")
+            })?;
+            FontArc::try_from_vec(data).map_err(|_| format!("{} není font", path))
+        };
+        Ok(Self {
+            regular: load("CARD_FONT", DEFAULT_FONT)?,
+            bold: load("CARD_FONT_BOLD", DEFAULT_BOLD_FONT)?,
+        })
+    }
+}
+
+// `arg` is a day like in `obedy`, or "tyden" for the whole week
+pub async fn card_message(
+    canteen_id: u32,
+    guild_id: Option<&str>,
+    arg: &str,
+) -> Result<CreateObedy, String> {
+    let today = lunch_fetch::today();
+    let (title, dates) = if matches!(arg, "tyden" | "týden") {
+        let monday = lunch_fetch::week_monday(today);
+        let friday = monday + Days::new(4);
+        (
+            format!(
+                "Obědy {} – {}",
+                monday.format("%-d.%-m."),
+                friday.format("%-d.%-m.")
+            ),
+            monday.iter_days().take(5).collect::<Vec<_>>(),
+        )
+    } else {
+        let day = lunch_fetch::parse_day(arg, today).ok_or("Nechápu, kterej den chceš 🤔")?;
+        (format!("Obědy · {}", day_title(day)), vec![day])
+    };
+
+    let provider = lunch_fetch::guild_provider(guild_id);
+    let menus = join_all(
+        dates
+            .iter()
+            .map(|date| provider.fetch_day(canteen_id, *date)),
+    )
+    .await;
+    let mut foods = Vec::with_capacity(dates.len());
+    for menu in menus {
+        match menu {
+            Ok(offers) => foods.push(lunch_fetch::day_foods(offers)),
+            Err(why) => return Err(provider.failed(why)),
+        }
+    }
+    if foods.iter().all(|foods| foods.is_empty()) {
+        return Err(String::from("Na tento den nejsou žádné obědy"));
+    }
+
+    // same as the embeds, a missing photo just leaves an empty square
+    let mut photos = join_all(foods.iter().flatten().map(|food| async move {
+        match provider.fetch_image(food.id).await.ok().flatten() {
+            Some(url) => download_photo(&url).await,
+            None => {
+                let community = lunch_fetch::community_photo(guild_id, food.id)?;
+                load_photo(&community.data)
+            }
+        }
+    }))
+    .await
+    .into_iter();
+
+    let days = dates
+        .iter()
+        .zip(&foods)
+        .map(|(date, foods)| Day {
+            title: day_title(*date),
+            dishes: foods
+                .iter()
+                .map(|food| Dish {
+                    name: food.name.clone(),
+                    rating: format!(
+                        "★ {}",
+                        lunch_fetch::rating_text(
+                            food.average_rating,
+                            lunch_fetch::server_rating(guild_id, food.id),
+                        )
+                    ),
+                    photo: photos.next().flatten(),
+                })
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+
+    let card = render(&title, &days, &Fonts::load()?);
+    let mut png = Cursor::new(Vec::new());
+    if let Err(why) = DynamicImage::ImageRgba8(card).write_to(&mut png, ImageFormat::Png) {
+        println!("Failed to encode menu card: {}", why);
+        return Err(String::from("Nepodařilo se nakreslit obrázek"));
+    }
+
+    Ok(CreateObedy {
+        embeds: Vec::new(),
+        components: Vec::new(),
+        attachments: vec![CreateAttachment::bytes(png.into_inner(), "obedy.png")],
+    })
+}
+
+fn day_title(date: NaiveDate) -> String {
+    format!(
+        "{} {}",
+        crate::czech_weekday(date.weekday()),
+        date.format("%-d.%-m.")
+    )
+}
+
+async fn download_photo(url: &str) -> Option<DynamicImage> {
+    let response = crate::CLIENT.get(url).send().await.ok()?;
+    let bytes = response.bytes().await.ok()?;
+    load_photo(&bytes)
+}
+
+fn load_photo(bytes: &[u8]) -> Option<DynamicImage> {
+    let photo = image::load_from_memory(bytes).ok()?;
+    Some(photo.resize_to_fill(PHOTO_SIZE, PHOTO_SIZE, FilterType::Triangle))
+}
+
+fn render(title: &str, days: &[Day], fonts: &Fonts) -> RgbaImage {
+    // a single day doesn't need its own heading
+    let show_days = days.len() > 1;
+    let height = HEADER_HEIGHT
+        + days
+            .iter()
+            .map(|day| {
+                let heading = if show_days { DAY_HEIGHT } else { 0 };
+                heading + ROW_HEIGHT * day.dishes.len().max(1) as u32
+            })
+            .sum::<u32>()
+        + PADDING as u32;
+
+    let mut card = RgbaImage::from_pixel(WIDTH, height, BACKGROUND);
+    draw_filled_rect_mut(
+        &mut card,
+        Rect::at(0, 0).of_size(WIDTH, HEADER_HEIGHT),
+        PINK,
+    );
+    draw_text_mut(
+        &mut card,
+        WHITE,
+        PADDING,
+        18,
+        PxScale::from(32.0),
+        &fonts.bold,
+        title,
+    );
+
+    let mut y = HEADER_HEIGHT as i32;
+    for day in days {
+        if show_days {
+            draw_text_mut(
+                &mut card,
+                PINK,
+                PADDING,
+                y + 12,
+                PxScale::from(24.0),
+                &fonts.bold,
+                &day.title,
+            );
+            y += DAY_HEIGHT as i32;
+        }
+        if day.dishes.is_empty() {
+            draw_text_mut(
+                &mut card,
+                MUTED,
+                PADDING,
+                y + 10,
+                PxScale::from(22.0),
+                &fonts.regular,
+                "Žádné obědy",
+            );
+            y += ROW_HEIGHT as i32;
+            continue;
+        }
+
+        for (index, dish) in day.dishes.iter().enumerate() {
+            let top = y + 10;
+            match &dish.photo {
+                Some(photo) => imageops::overlay(&mut card, photo, PADDING as i64, top as i64),
+                None => draw_filled_rect_mut(
+                    &mut card,
+                    Rect::at(PADDING, top).of_size(PHOTO_SIZE, PHOTO_SIZE),
+                    PLACEHOLDER,
+                ),
+            }
+
+            let number_x = PADDING + PHOTO_SIZE as i32 + 20;
+            draw_text_mut(
+                &mut card,
+                PINK,
+                number_x,
+                top,
+                PxScale::from(24.0),
+                &fonts.bold,
+                &format!("{}.", index + 1),
+            );
+
+            let text_x = number_x + 40;
+            let name_scale = PxScale::from(22.0);
+            let max_width = (WIDTH as i32 - text_x - PADDING) as u32;
+            for (line, text) in wrap(&dish.name, max_width, name_scale, &fonts.bold)
+                .iter()
+                .enumerate()
+            {
+                draw_text_mut(
+                    &mut card,
+                    TEXT,
+                    text_x,
+                    top + 2 + line as i32 * 26,
+                    name_scale,
+                    &fonts.bold,
+                    text,
+                );
+            }
+            draw_text_mut(
+                &mut card,
+                MUTED,
+                text_x,
+                top + 58,
+                PxScale::from(18.0),
+                &fonts.regular,
+                &dish.rating,
+            );
+            y += ROW_HEIGHT as i32;
+        }
+    }
+    card
+}
+
+// Splits the name into at most two lines, cutting off the rest with "…"
+fn wrap(text: &str, width: u32, scale: PxScale, font: &FontArc) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut words = text.split_whitespace().peekable();
+    while let Some(word) = words.next() {
+        let candidate = if current.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", current, word)
+        };
+        if current.is_empty() || text_size(scale, font, &candidate).0 <= width {
+            current = candidate;
+            continue;
+        }
+        lines.push(current);
+        if lines.len() == 1 {
+            current = word.to_string();
+            continue;
+        }
+        // second line is full, everything else goes into the ellipsis
+        current = String::new();
+        let rest = std::iter::once(word)
+            .chain(words.by_ref())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let last = lines.pop().unwrap_or_default();
+        lines.push(ellipsis(&format!("{} {}", last, rest), width, scale, font));
+    }
+    if !current.is_empty() {
+        lines.push(ellipsis(&current, width, scale, font));
+    }
+    lines
+}
+
+fn ellipsis(text: &str, width: u32, scale: PxScale, font: &FontArc) -> String {
+    if text_size(scale, font, text).0 <= width {
+        return text.to_string();
+    }
+    let mut chars = text.chars().collect::<Vec<_>>();
+    while chars.pop().is_some() {
+        let cut = format!("{}…", chars.iter().collect::<String>().trim_end());
+        if text_size(scale, font, &cut).0 <= width {
+            return cut;
+        }
+    }
+    String::new()
+}
@@ -1,8 +1,12 @@
 use std::process::Command;
 
+use scraper::{ElementRef, Html, Selector};
+use serde_json::Value;
 use serenity::builder::{CreateAttachment, CreateEmbed, CreateMessage};
 use serenity::model::Color;
 
+const TIMETABLE_URL: &str = "https://bakalari.gypce.cz/bakaweb/Timetable/Public";
+
 #[allow(dead_code)]
 pub struct CreateRozvrh {
     pub attachment: CreateAttachment,
@@ -16,7 +20,142 @@ where
 {
     let class = args.next().unwrap_or("7B");
     let time = args.next().unwrap_or("0");
-    let (arg, mode) = match class {
+    let path = match timetable_url(class, time == "+1") {
+        Some(path) => path,
+        None => return Err("Nechápu, kterej rozvrh chceš 🤔".into()),
+    };
+
+    Command::new("wkhtmltoimage")
+        .args([
+            "--run-script",
+            "document.getElementById('c-p-bn').click()",
+            &path,
+            "/tmp/rozvrh.png",
+        ])
+        .status()?;
+
+    let rozvrh_image = &tokio::fs::File::open("/tmp/rozvrh.png").await?;
+
+    let attachment = CreateAttachment::file(rozvrh_image, "rozvrh.png").await?;
+    let embed = CreateEmbed::new()
+        .title(format!("rozvrh pro {}", class))
+        .attachment("rozvrh.png")
+        .color(Color::from_rgb(5, 180, 255));
+    let message = CreateMessage::new()
+        .add_file(attachment.clone())
+        .embed(embed.clone());
+
+    Ok(CreateRozvrh {
+        attachment,
+        embed,
+        message,
+    })
+}
+
+// Public timetable page of a class or room, this week or the next one
+fn timetable_url(name: &str, next_week: bool) -> Option<String> {
+    let (id, mode) = timetable_id(name)?;
+    let time = if next_week { "Next" } else { "Actual" };
+    Some(format!("{}/{}/{}/{}", TIMETABLE_URL, time, mode, id))
+}
+
+// Timetable as text for the chatbot, errors are text too so the model can explain them
+pub async fn rozvrh_text(name: &str, next_week: bool) -> String {
+    let Some(url) = timetable_url(name, next_week) else {
+        return format!("Rozvrh {} neexistuje", name);
+    };
+    let html = match fetch_page(&url).await {
+        Ok(html) => html,
+        Err(why) => return format!("Rozvrh se nepodařilo načíst: {}", why),
+    };
+    let days = parse_rozvrh(&html);
+    if days.is_empty() {
+        return format!("Rozvrh {} je prázdný", name);
+    }
+    days.join("\n")
+}
+
+async fn fetch_page(url: &str) -> Result<String, reqwest::Error> {
+    crate::CLIENT
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await
+}
+
+// One line per day, lessons are read from the detail Bakaláři shows on hover
+fn parse_rozvrh(html: &str) -> Vec<String> {
+    let document = Html::parse_document(html);
+    let row_selector = Selector::parse(".bk-timetable-row").expect("valid selector");
+    let day_selector = Selector::parse(".bk-day-day").expect("valid selector");
+    let date_selector = Selector::parse(".bk-day-date").expect("valid selector");
+    let lesson_selector = Selector::parse("[data-detail]").expect("valid selector");
+
+    let text = |element: Option<ElementRef>| {
+        element
+            .map(|element| element.text().collect::<String>().trim().to_string())
+            .unwrap_or_default()
+    };
+    let mut days = Vec::new();
+    for row in document.select(&row_selector) {
+        let day = text(row.select(&day_selector).next());
+        if day.is_empty() {
+            continue;
+        }
+        let date = text(row.select(&date_selector).next());
+        let lessons = row
+            .select(&lesson_selector)
+            .filter_map(|lesson| serde_json::from_str::<Value>(lesson.attr("data-detail")?).ok())
+            .filter_map(|detail| lesson_text(&detail))
+            .collect::<Vec<_>>();
+        let lessons = match lessons.is_empty() {
+            true => String::from("volno"),
+            false => lessons.join("; "),
+        };
+        days.push(format!("{} {}: {}", day, date, lessons));
+    }
+    days
+}
+
+// subjecttext looks like "Matematika | po 20.10. | 1 (8:00 - 8:45)"
+fn lesson_text(detail: &Value) -> Option<String> {
+    let field = |key: &str| {
+        detail[key]
+            .as_str()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+    // cancelled lessons only have the reason
+    if let Some(removed) = field("removedinfo") {
+        return Some(format!("odpadá ({})", removed));
+    }
+    let subject = field("subjecttext")?;
+    let mut parts = subject.split(" | ");
+    let name = parts.next()?;
+    let mut line = match parts
+        .nth(1)
+        .map(|hour| hour.split_once(' ').unwrap_or((hour, "")))
+    {
+        Some((hour, time)) => format!("{}. hod {} {}", hour, time, name),
+        None => name.to_string(),
+    };
+    for value in [field("teacher"), field("room"), field("group")]
+        .into_iter()
+        .flatten()
+    {
+        line.push_str(&format!(", {}", value));
+    }
+    if let Some(change) = field("changeinfo") {
+        line.push_str(&format!(" ({})", change));
+    }
+    Some(line)
+}
+
+// Maps a class or room name to its Bakaláři timetable id and mode
+pub fn timetable_id(name: &str) -> Option<(&'static str, &'static str)> {
+    Some(match name {
         // Třídy (Classes)
         "1A" => ("2G", "Class"),
         "1B" => ("2I", "Class"),
@@ -96,45 +235,14 @@ where
         "V4" => ("5H", "Room"),
 
         // Default
-        _ => return Err("Nechápu, kterej rozvrh chceš 🤔".into()),
-    };
-    let time = match time {
-        "+1" => "Next",
-        _ => "Actual",
-    };
-
-    let path = format!(
-        "https://bakalari.gypce.cz/bakaweb/Timetable/Public/{}/{}/{}",
-        time, mode, arg
-    );
-
-    Command::new("wkhtmltoimage")
-        .args([
-            "--run-script",
-            "document.getElementById('c-p-bn').click()",
-            &path,
-            "/tmp/rozvrh.png",
-        ])
-        .status()?;
-
-    let rozvrh_image = &tokio::fs::File::open("/tmp/rozvrh.png").await?;
-
-    let attachment = CreateAttachment::file(rozvrh_image, "rozvrh.png").await?;
-    let embed = CreateEmbed::new()
-        .title(format!("rozvrh pro {}", class))
-        .attachment("rozvrh.png")
-        .color(Color::from_rgb(5, 180, 255));
-    let message = CreateMessage::new()
-        .add_file(attachment.clone())
-        .embed(embed.clone());
-
-    Ok(CreateRozvrh {
-        attachment,
-        embed,
-        message,
+        _ => return None,
     })
 }
 
+pub fn is_class(name: &str) -> bool {
+    matches!(timetable_id(name), Some((_, "Class")))
+}
+
 // slash command pro rozvrh
 use serenity::builder::{CreateCommand, CreateCommandOption};
 use serenity::model::application::CommandOptionType;
@@ -168,3 +276,32 @@ pub const COMMAND: SlashCommand = SlashCommand {
     register,
     help: help_message,
 };
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = include_str!("../tests/fixtures/rozvrh.html");
+
+    #[test]
+    fn parses_saved_page() {
+        assert_eq!(
+            parse_rozvrh(SAMPLE),
+            vec![
+                String::from(
+                    "po 20.10.: 1. hod (8:00 - 8:45) Matematika, Mgr. Jan Novák, 203; 2. hod (8:55 - 9:40) Anglický jazyk, Mgr. Eva Dvořáková, J2, an1 (Suplování); odpadá (Nj, sk2 – exkurze)"
+                ),
+                String::from("út 21.10.: volno"),
+            ]
+        );
+    }
+
+    #[test]
+    fn builds_urls() {
+        assert_eq!(
+            timetable_url("7B", true).as_deref(),
+            Some("https://bakalari.gypce.cz/bakaweb/Timetable/Public/Next/Class/1T")
+        );
+        assert_eq!(timetable_url("9Z", false), None);
+    }
+}
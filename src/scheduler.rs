@@ -0,0 +1,249 @@
+// Background jobs that run without anyone asking, like the daily lunch post or food alerts
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use chrono::{Datelike, Days, NaiveDate, NaiveTime, Weekday};
+use serenity::{
+    builder::CreateMessage,
+    http::Http,
+    model::id::{ChannelId, UserId},
+};
+
+use crate::{
+    database::Database,
+    lunch_alerts,
+    lunch_fetch::{self, Food, LunchProvider},
+    lunch_stats,
+};
+
+// How late a post may still go out, eg. after a restart
+const POST_WINDOW_MINUTES: i64 = 15;
+// When users get told about tomorrow's favourite (or hated) dishes
+const ALERT_TIME: &str = "18:00";
+// When menus go into the archive for statistics, after lunch so the ratings settle
+const ARCHIVE_TIME: &str = "15:00";
+// How many days back get archived, so days the bot was offline get filled in
+const ARCHIVE_DAYS: u64 = 7;
+
+// ready gets called again on every reconnect, we only want one scheduler
+static STARTED: AtomicBool = AtomicBool::new(false);
+
+pub fn start(http: Arc<Http>) {
+    if STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut last_alerted: Option<NaiveDate> = None;
+        let mut last_archived: Option<NaiveDate> = None;
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            post_lunches(&http).await;
+            send_food_alerts(&http, &mut last_alerted).await;
+            archive_lunches(&mut last_archived).await;
+        }
+    });
+}
+
+async fn post_lunches(http: &Http) {
+    let now = lunch_fetch::now();
+    let today = now.date_naive();
+    if matches!(today.weekday(), Weekday::Sat | Weekday::Sun) {
+        return;
+    }
+
+    let data = match Database::get_data() {
+        Ok(data) => data,
+        Err(why) => {
+            println!("Failed to get database: {}", why);
+            return;
+        }
+    };
+
+    for (guild_id, server) in data {
+        let (Some(channel_id), Some(time)) = (&server.lunch_channel, &server.lunch_time) else {
+            continue;
+        };
+        let Ok(time) = NaiveTime::parse_from_str(time, "%H:%M") else {
+            continue;
+        };
+        let minutes_late = (now.time() - time).num_minutes();
+        if !(0..POST_WINDOW_MINUTES).contains(&minutes_late) || server.lunch_posted == Some(today) {
+            continue;
+        }
+        // stored before sending, a failed save would otherwise post every minute
+        if let Err(why) = Database::set_lunch_posted(guild_id.clone(), today) {
+            println!("Failed to save lunch post of {}: {}", guild_id, why);
+            continue;
+        }
+
+        let channel_id = match channel_id.parse::<u64>() {
+            Ok(id) => ChannelId::new(id),
+            Err(why) => {
+                println!("Failed to parse channel id: {}", why);
+                continue;
+            }
+        };
+        // days without offers (holidays) just get skipped
+        let obedy = match lunch_fetch::get_lunch_embed(
+            lunch_fetch::guild_canteen(Some(guild_id.clone())),
+            today,
+            Some(&guild_id),
+        )
+        .await
+        {
+            Ok(obedy) => obedy,
+            Err(why) => {
+                println!("Skipping lunch post to {}: {}", channel_id, why);
+                continue;
+            }
+        };
+        if let Err(why) = channel_id
+            .send_message(
+                http,
+                CreateMessage::new()
+                    .embeds(obedy.embeds)
+                    .components(obedy.components)
+                    .add_files(obedy.attachments),
+            )
+            .await
+        {
+            println!("Failed to send lunches to channel {}: {}", channel_id, why);
+        }
+    }
+}
+
+async fn send_food_alerts(http: &Http, last_alerted: &mut Option<NaiveDate>) {
+    let now = lunch_fetch::now();
+    let today = now.date_naive();
+    let Ok(time) = NaiveTime::parse_from_str(ALERT_TIME, "%H:%M") else {
+        return;
+    };
+    let minutes_late = (now.time() - time).num_minutes();
+    if !(0..POST_WINDOW_MINUTES).contains(&minutes_late) || *last_alerted == Some(today) {
+        return;
+    }
+    *last_alerted = Some(today);
+
+    let tomorrow = today + Days::new(1);
+    if matches!(tomorrow.weekday(), Weekday::Sat | Weekday::Sun) {
+        return;
+    }
+    let users = match Database::get_users() {
+        Ok(users) => users,
+        Err(why) => {
+            println!("Failed to get database: {}", why);
+            return;
+        }
+    };
+
+    // users often share a canteen, fetch each one only once
+    let mut menus: HashMap<(&str, u32), Vec<Food>> = HashMap::new();
+    for (user_id, user) in users {
+        if user.liked_foods.is_empty() && user.hated_foods.is_empty() {
+            continue;
+        }
+        let Ok(id) = user_id.parse::<u64>() else {
+            continue;
+        };
+
+        let canteen_id = lunch_fetch::canteen_for(user.alert_guild.clone(), &user_id);
+        let provider = lunch_fetch::guild_provider(user.alert_guild.as_deref());
+        let key = (provider.name(), canteen_id);
+        if let Entry::Vacant(entry) = menus.entry(key) {
+            let foods = match provider.fetch_day(canteen_id, tomorrow).await {
+                Ok(offers) => lunch_fetch::day_foods(offers),
+                Err(why) => {
+                    println!(
+                        "Failed fetching lunches for alerts from {}: {why}",
+                        provider.name()
+                    );
+                    Vec::new()
+                }
+            };
+            entry.insert(foods);
+        }
+        let lines = lunch_alerts::alert_lines(&user, &menus[&key]);
+        if lines.is_empty() {
+            continue;
+        }
+
+        if let Err(why) = UserId::new(id)
+            .direct_message(http, CreateMessage::new().content(lines.join("\n")))
+            .await
+        {
+            println!("Failed to send food alert to {}: {}", user_id, why);
+        }
+    }
+}
+
+// Menus of every canteen in use go into the archive, future days stay out of it
+async fn archive_lunches(last_archived: &mut Option<NaiveDate>) {
+    let now = lunch_fetch::now();
+    let today = now.date_naive();
+    let Ok(time) = NaiveTime::parse_from_str(ARCHIVE_TIME, "%H:%M") else {
+        return;
+    };
+    if now.time() < time || *last_archived == Some(today) {
+        return;
+    }
+    *last_archived = Some(today);
+
+    let (data, users) = match (Database::get_data(), Database::get_users()) {
+        (Ok(data), Ok(users)) => (data, users),
+        (Err(why), _) | (_, Err(why)) => {
+            println!("Failed to get database: {}", why);
+            return;
+        }
+    };
+    // (provider, canteen id), the default canteen is used in DMs
+    let mut canteens: HashMap<(&str, u32), &dyn LunchProvider> = HashMap::new();
+    let mut add = |guild_id: Option<String>, canteen_id: u32| {
+        let provider = lunch_fetch::guild_provider(guild_id.as_deref());
+        canteens.insert((provider.name(), canteen_id), provider);
+    };
+    add(None, lunch_fetch::guild_canteen(None));
+    for guild_id in data.into_keys() {
+        add(
+            Some(guild_id.clone()),
+            lunch_fetch::guild_canteen(Some(guild_id)),
+        );
+    }
+    for user in users.into_values() {
+        if let Some(canteen_id) = user.canteen_id {
+            add(user.alert_guild, canteen_id);
+        }
+    }
+
+    for ((_, canteen_id), provider) in canteens {
+        let from = today - Days::new(ARCHIVE_DAYS);
+        let offers = match provider.fetch_range(canteen_id, from, today).await {
+            Ok(offers) => offers,
+            Err(why) => {
+                println!(
+                    "Failed fetching lunches to archive from {}: {why}",
+                    provider.name()
+                );
+                continue;
+            }
+        };
+        let records = lunch_stats::lunch_records(canteen_id, &offers);
+        // the whole archive gets rewritten, that doesn't belong on the async threads
+        let archived = tokio::task::spawn_blocking(move || {
+            Database::archive_obedy(records).map_err(|why| why.to_string())
+        })
+        .await;
+        match archived {
+            Ok(Ok(_)) => {}
+            Ok(Err(why)) => println!("Failed to archive lunches: {}", why),
+            Err(why) => println!("Failed to archive lunches: {}", why),
+        }
+    }
+}
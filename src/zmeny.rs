@@ -1,11 +1,20 @@
+use std::collections::HashMap;
 use std::process::Command;
 
+use chrono::{Datelike, Days, Months, NaiveDate};
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::{Deserialize, Serialize};
 use serenity::builder::{CreateAttachment, CreateEmbed, CreateMessage};
 use serenity::model::Color;
 
+use crate::database::Database;
+
+const ZMENY_URL: &str = "https://bakalari.gypce.cz/bakaweb/next/zmeny.aspx";
+
 #[allow(dead_code)]
 pub struct CreateZmeny {
-    pub attachment: CreateAttachment,
+    // missing when there is nothing to show
+    pub attachment: Option<CreateAttachment>,
     pub embed: CreateEmbed,
     pub message: CreateMessage,
 }
@@ -15,6 +24,178 @@ where
     I: Iterator<Item = &'a str>,
 {
     let arg = args.next().unwrap_or("7B");
+    let mode = args.next();
+    if !crate::rozvrh::is_class(arg) {
+        return Err(format!(
+            "Třída {} neexistuje 🤔 piš ji s velkým písmenem a bez tečky, eg. **7B**",
+            arg
+        )
+        .into());
+    }
+    if mode == Some("export") {
+        return export(arg, args).await;
+    }
+    let with_image = mode == Some("obrazek");
+
+    let mut embed = CreateEmbed::new()
+        .title(format!("Změny třídy {}", arg))
+        .color(Color::from_rgb(5, 180, 255));
+
+    // if the page can't be parsed we fall back to the screenshot
+    let zmeny = match fetch_zmeny().await {
+        Ok(zmeny) => Some(
+            zmeny
+                .into_iter()
+                .filter(|zmena| zmena.class == arg)
+                .collect::<Vec<_>>(),
+        ),
+        Err(why) => {
+            println!("Failed parsing changes: {why:?}");
+            None
+        }
+    };
+
+    match &zmeny {
+        Some(zmeny) if zmeny.is_empty() => {
+            return Ok(CreateZmeny {
+                attachment: None,
+                message: CreateMessage::new().embed(embed.clone().description("Žádné změny 🎉")),
+                embed: embed.description("Žádné změny 🎉"),
+            });
+        }
+        Some(zmeny) => {
+            // one field per day, the changes are already sorted by the page
+            let mut days: Vec<(NaiveDate, Vec<String>)> = Vec::new();
+            for zmena in zmeny {
+                let line = zmena.line();
+                match days.last_mut() {
+                    Some((date, lines)) if *date == zmena.date => lines.push(line),
+                    _ => days.push((zmena.date, vec![line])),
+                }
+            }
+            for (date, lines) in days {
+                embed = embed.field(
+                    format!(
+                        "{} {}",
+                        crate::czech_weekday(date.weekday()),
+                        date.format("%-d.%-m.")
+                    ),
+                    crate::truncate(&lines.join("\n"), 1024),
+                    false,
+                );
+            }
+        }
+        None => {}
+    }
+
+    let attachment = if with_image || zmeny.is_none() {
+        embed = embed.attachment("zmeny.png");
+        Some(screenshot(arg).await?)
+    } else {
+        None
+    };
+    let mut message = CreateMessage::new().embed(embed.clone());
+    if let Some(attachment) = &attachment {
+        message = message.add_file(attachment.clone());
+    }
+
+    Ok(CreateZmeny {
+        attachment,
+        embed,
+        message,
+    })
+}
+
+// Changes of a class as plain text, for the chatbot
+pub async fn zmeny_text(class: &str) -> String {
+    if !crate::rozvrh::is_class(class) {
+        return format!("Třída {} neexistuje", class);
+    }
+    let zmeny = match fetch_zmeny().await {
+        Ok(zmeny) => zmeny,
+        Err(why) => return format!("Změny se nepodařilo načíst: {}", why),
+    };
+    let lines = zmeny
+        .iter()
+        .filter(|zmena| zmena.class == class)
+        .map(|zmena| {
+            format!(
+                "{} {}: {}",
+                crate::czech_weekday(zmena.date.weekday()),
+                zmena.date.format("%-d.%-m."),
+                zmena.line()
+            )
+        })
+        .collect::<Vec<_>>();
+    if lines.is_empty() {
+        return format!("Třída {} nemá žádné změny", class);
+    }
+    lines.join("\n")
+}
+
+// Parsed changes of a class as a json or csv attachment
+async fn export<'a, I>(class: &str, mut args: I) -> Result<CreateZmeny, Box<dyn std::error::Error>>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let format = match args.next() {
+        Some(format @ ("json" | "csv")) => format,
+        _ => return Err("Nechápu formát 🤔 zkus `json` nebo `csv`".into()),
+    };
+    let today = chrono::Local::now().date_naive();
+
+    // without a range we export what is on the page right now
+    let (label, zmeny) = match args.next() {
+        None => (String::from("aktuální změny"), fetch_zmeny().await?),
+        Some(from) => {
+            let period = Period::from_args(from, args.next(), today).ok_or(
+                "Nechápu období 🤔 zkus `tyden`, `mesic`, `pololeti`, `rok` nebo data od a do, eg. `1.9.2025 31.1.2026`",
+            )?;
+            // refresh the archive first, it's fine if the page is down
+            if let Err(why) = fetch_zmeny().await {
+                println!("Failed parsing changes: {why:?}");
+            }
+            let zmeny = Database::get_zmeny()?
+                .into_iter()
+                .filter(|zmena| period.contains(zmena.date))
+                .collect();
+            (period.label, zmeny)
+        }
+    };
+    let zmeny = zmeny
+        .into_iter()
+        .filter(|zmena| zmena.class == class)
+        .collect::<Vec<_>>();
+
+    let data = match format {
+        "json" => serde_json::to_vec_pretty(&zmeny)?,
+        _ => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for zmena in &zmeny {
+                writer.serialize(zmena)?;
+            }
+            writer.into_inner()?
+        }
+    };
+
+    let attachment = CreateAttachment::bytes(data, format!("zmeny_{}.{}", class, format));
+    let embed = CreateEmbed::new()
+        .title(format!("Export změn třídy {}", class))
+        .description(format!("{}: **{}** záznamů", label, zmeny.len()))
+        .color(Color::from_rgb(5, 180, 255));
+    let message = CreateMessage::new()
+        .add_file(attachment.clone())
+        .embed(embed.clone());
+
+    Ok(CreateZmeny {
+        attachment: Some(attachment),
+        embed,
+        message,
+    })
+}
+
+// Screenshot of the substitution page with other classes hidden
+async fn screenshot(arg: &str) -> Result<CreateAttachment, Box<dyn std::error::Error>> {
     let class = arg
         .chars()
         .map(|ch| ch.to_string())
@@ -25,26 +206,422 @@ where
         .args([
             "--run-script",
             &format!("[].forEach.call(document.querySelectorAll('table.datagrid > tbody > tr'), function(row) {{ if (row.firstElementChild && row.firstElementChild.textContent.trim() !== '{}') {{ row.style.display = 'none'; }} }});", class),
-            "https://bakalari.gypce.cz/bakaweb/next/zmeny.aspx",
+            ZMENY_URL,
             "/tmp/zmeny.png"
         ]).status()?;
 
     let zmeny_image = &tokio::fs::File::open("/tmp/zmeny.png").await?;
 
-    let attachment = CreateAttachment::file(zmeny_image, "zmeny.png").await?;
-    let embed = CreateEmbed::new()
-        .title(format!("Změny třídy {}", arg))
-        .attachment("zmeny.png")
-        .color(Color::from_rgb(5, 180, 255));
-    let message = CreateMessage::new()
-        .add_file(attachment.clone())
-        .embed(embed.clone());
+    Ok(CreateAttachment::file(zmeny_image, "zmeny.png").await?)
+}
 
-    Ok(CreateZmeny {
-        attachment,
-        embed,
-        message,
-    })
+// One row of the substitution table
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Zmena {
+    pub date: NaiveDate,
+    // class without the dot, eg. "7B"
+    pub class: String,
+    pub hour: String,
+    pub subject: String,
+    pub group: String,
+    pub room: String,
+    pub teacher: String,
+    pub change: String,
+}
+
+impl Zmena {
+    // Short form for embeds, eg. "3. hod – Fy – odpadá"
+    pub fn line(&self) -> String {
+        let mut line = format!("{}. hod – {}", self.hour, self.subject);
+        if !self.group.is_empty() {
+            line.push_str(&format!(" ({})", self.group));
+        }
+        for part in [&self.change, &self.teacher, &self.room] {
+            if !part.is_empty() {
+                line.push_str(&format!(" – {}", part));
+            }
+        }
+        line
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        let change = self.change.to_lowercase();
+        change.contains("odpad") || change.contains("zruš")
+    }
+
+    pub fn is_substitution(&self) -> bool {
+        let change = self.change.to_lowercase();
+        change.contains("supl") || change.contains("zastup")
+    }
+}
+
+// Downloads the substitution page, parses it and stores the result in the archive
+pub async fn fetch_zmeny() -> Result<Vec<Zmena>, Box<dyn std::error::Error>> {
+    let html = reqwest::get(ZMENY_URL)
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let zmeny = parse_zmeny(&html, chrono::Local::now().date_naive());
+
+    if let Err(why) = Database::archive_zmeny(&zmeny) {
+        println!("Failed archiving changes: {why:?}");
+    }
+    Ok(zmeny)
+}
+
+// Column positions in the datagrid, read from its header row
+struct Columns {
+    class: usize,
+    hour: usize,
+    subject: usize,
+    group: usize,
+    room: usize,
+    teacher: usize,
+    change: usize,
+}
+
+impl Columns {
+    fn from_header(header: &[String]) -> Option<Self> {
+        let find = |keys: &[&str]| {
+            header.iter().position(|cell| {
+                let cell = cell.to_lowercase();
+                keys.iter().any(|key| cell.contains(key))
+            })
+        };
+        Some(Self {
+            // tables without a class column list changes of teachers, we don't want those
+            class: find(&["tříd"])?,
+            hour: find(&["hod"]).unwrap_or(1),
+            subject: find(&["předm"]).unwrap_or(2),
+            group: find(&["sk"]).unwrap_or(3),
+            room: find(&["učeb", "míst"]).unwrap_or(4),
+            teacher: find(&["vyuč", "učitel"]).unwrap_or(5),
+            change: find(&["změn", "pozn", "typ"]).unwrap_or(6),
+        })
+    }
+}
+
+impl Default for Columns {
+    fn default() -> Self {
+        Self {
+            class: 0,
+            hour: 1,
+            subject: 2,
+            group: 3,
+            room: 4,
+            teacher: 5,
+            change: 6,
+        }
+    }
+}
+
+fn parse_zmeny(html: &str, today: NaiveDate) -> Vec<Zmena> {
+    let document = Html::parse_document(html);
+    let table_selector = Selector::parse("table.datagrid").expect("valid selector");
+    let row_selector = Selector::parse("tr").expect("valid selector");
+    let header_selector = Selector::parse("th").expect("valid selector");
+    let cell_selector = Selector::parse("th, td").expect("valid selector");
+
+    let mut zmeny = Vec::new();
+    for table in document.select(&table_selector) {
+        let date = table_date(table, today).unwrap_or(today);
+        let mut columns = Some(Columns::default());
+        // the class is only written on the first row of each class in some layouts
+        let mut last_class = String::new();
+
+        for row in table.select(&row_selector) {
+            let cells = row
+                .select(&cell_selector)
+                .map(|cell| cell.text().collect::<String>().trim().to_string())
+                .collect::<Vec<_>>();
+            if row.select(&header_selector).next().is_some() {
+                columns = Columns::from_header(&cells);
+                continue;
+            }
+            let Some(columns) = &columns else { continue };
+            if cells.iter().all(|cell| cell.is_empty()) {
+                continue;
+            }
+
+            let cell = |index: usize| cells.get(index).cloned().unwrap_or_default();
+            let class = cell(columns.class).replace(['.', ' '], "");
+            if !class.is_empty() {
+                last_class = class;
+            }
+
+            zmeny.push(Zmena {
+                date,
+                class: last_class.clone(),
+                hour: cell(columns.hour).trim_end_matches('.').to_string(),
+                subject: cell(columns.subject),
+                group: cell(columns.group),
+                room: cell(columns.room),
+                teacher: cell(columns.teacher),
+                change: cell(columns.change),
+            });
+        }
+    }
+    zmeny
+}
+
+// Looks for the day heading closest above the table (or its caption)
+fn table_date(table: ElementRef, today: NaiveDate) -> Option<NaiveDate> {
+    let caption_selector = Selector::parse("caption").expect("valid selector");
+    if let Some(caption) = table.select(&caption_selector).next()
+        && let Some(date) = find_date(&caption.text().collect::<String>(), today)
+    {
+        return Some(date);
+    }
+
+    let mut node = Some(*table);
+    while let Some(current) = node {
+        for sibling in current.prev_siblings() {
+            let text = match (ElementRef::wrap(sibling), sibling.value()) {
+                (Some(element), _) => element.text().collect::<String>(),
+                (None, Node::Text(text)) => text.to_string(),
+                _ => continue,
+            };
+            if let Some(date) = find_date(&text, today) {
+                return Some(date);
+            }
+        }
+        node = current.parent();
+    }
+    None
+}
+
+// Finds dates written as "20.10.2025", "20. 10. 2025" or "20.10."
+fn find_date(text: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let compact = text.split_whitespace().collect::<String>();
+    compact
+        .split(|ch: char| !ch.is_ascii_digit() && ch != '.')
+        .find_map(|token| {
+            let numbers = token
+                .split('.')
+                .filter(|part| !part.is_empty())
+                .map(|part| part.parse::<u32>().ok())
+                .collect::<Option<Vec<_>>>()?;
+            match numbers[..] {
+                [day, month, year] => NaiveDate::from_ymd_opt(year as i32, month, day),
+                [day, month] if token.ends_with('.') => {
+                    NaiveDate::from_ymd_opt(today.year(), month, day)
+                }
+                _ => None,
+            }
+        })
+}
+
+// Accepts ISO dates as well as the czech format
+pub fn parse_date(arg: &str, today: NaiveDate) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(arg, "%Y-%m-%d")
+        .ok()
+        .or_else(|| find_date(arg, today))
+}
+
+// Time span used by archive queries
+pub struct Period {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub label: String,
+}
+
+impl Period {
+    pub fn parse(arg: &str, today: NaiveDate) -> Option<Self> {
+        let first_of_month = today.with_day(1)?;
+        // school year starts in September
+        let school_year = if today.month() >= 9 {
+            today.year()
+        } else {
+            today.year() - 1
+        };
+
+        let (from, to, label) = match arg {
+            "tyden" | "týden" => {
+                let monday = today - Days::new(today.weekday().num_days_from_monday() as u64);
+                (monday, monday + Days::new(6), "tento týden")
+            }
+            "mesic" | "měsíc" => (
+                first_of_month,
+                first_of_month + Months::new(1) - Days::new(1),
+                "tento měsíc",
+            ),
+            "pololeti" | "pololetí" => {
+                if (2..=8).contains(&today.month()) {
+                    (
+                        NaiveDate::from_ymd_opt(today.year(), 2, 1)?,
+                        NaiveDate::from_ymd_opt(today.year(), 8, 31)?,
+                        "toto pololetí",
+                    )
+                } else {
+                    (
+                        NaiveDate::from_ymd_opt(school_year, 9, 1)?,
+                        NaiveDate::from_ymd_opt(school_year + 1, 1, 31)?,
+                        "toto pololetí",
+                    )
+                }
+            }
+            "rok" => (
+                NaiveDate::from_ymd_opt(school_year, 9, 1)?,
+                NaiveDate::from_ymd_opt(school_year + 1, 8, 31)?,
+                "tento školní rok",
+            ),
+            _ => return None,
+        };
+        Some(Self {
+            from,
+            to,
+            label: label.to_string(),
+        })
+    }
+
+    // Named period or explicit dates, a single date means just that day
+    fn from_args(from: &str, to: Option<&str>, today: NaiveDate) -> Option<Self> {
+        if let Some(period) = Self::parse(from, today) {
+            return Some(period);
+        }
+        let from = parse_date(from, today)?;
+        let to = match to {
+            Some(to) => parse_date(to, today)?,
+            None => from,
+        };
+        Some(Self {
+            from,
+            to,
+            label: format!(
+                "{} – {}",
+                from.format("%-d.%-m.%Y"),
+                to.format("%-d.%-m.%Y")
+            ),
+        })
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.from <= date && date <= self.to
+    }
+}
+
+// Statistics over the substitution archive
+pub fn archiv_message<'a, I>(mut args: I) -> Result<CreateEmbed, Box<dyn std::error::Error>>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let today = chrono::Local::now().date_naive();
+    let zmeny = Database::get_zmeny()?;
+    let embed = CreateEmbed::new().color(Color::from_rgb(5, 180, 255));
+
+    match args.next() {
+        Some("odpadle") => {
+            let class = args.next().ok_or("Chybí třída, eg. `odpadle 7B`")?;
+            let period = Period::parse(args.next().unwrap_or("mesic"), today)
+                .ok_or("Neznámé období, zkus `tyden`, `mesic`, `pololeti` nebo `rok`")?;
+
+            let mut per_subject: HashMap<&str, u32> = HashMap::new();
+            let mut total = 0;
+            for zmena in zmeny.iter().filter(|zmena| {
+                zmena.class == class && zmena.is_cancelled() && period.contains(zmena.date)
+            }) {
+                total += 1;
+                *per_subject.entry(zmena.subject.as_str()).or_default() += 1;
+            }
+
+            let mut per_subject = per_subject.into_iter().collect::<Vec<_>>();
+            per_subject.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+            let breakdown = per_subject
+                .iter()
+                .map(|(subject, count)| format!("{subject}: **{count}**"))
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            Ok(embed
+                .title(format!("Odpadlé hodiny třídy {class}"))
+                .description(format!("{} odpadlo **{total}** hodin.", period.label))
+                .field(
+                    "Podle předmětu",
+                    if breakdown.is_empty() {
+                        String::from("Nic neodpadlo 🎉")
+                    } else {
+                        breakdown
+                    },
+                    false,
+                ))
+        }
+        Some("suplujici") => {
+            let period = Period::parse(args.next().unwrap_or("pololeti"), today)
+                .ok_or("Neznámé období, zkus `tyden`, `mesic`, `pololeti` nebo `rok`")?;
+
+            let mut per_teacher: HashMap<&str, u32> = HashMap::new();
+            for zmena in zmeny.iter().filter(|zmena| {
+                zmena.is_substitution() && !zmena.teacher.is_empty() && period.contains(zmena.date)
+            }) {
+                *per_teacher.entry(zmena.teacher.as_str()).or_default() += 1;
+            }
+
+            let mut per_teacher = per_teacher.into_iter().collect::<Vec<_>>();
+            per_teacher.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+            let ranking = per_teacher
+                .iter()
+                .take(10)
+                .enumerate()
+                .map(|(place, (teacher, count))| format!("{}. {teacher} · **{count}**", place + 1))
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            Ok(embed
+                .title(format!("Nejvíc suplování {}", period.label))
+                .description(if ranking.is_empty() {
+                    String::from("Žádné suplování v archivu")
+                } else {
+                    ranking
+                }))
+        }
+        Some("predmet") => {
+            let subject = args
+                .next()
+                .ok_or("Chybí předmět, eg. `predmet Fy`")?
+                .to_lowercase();
+            let period = Period::parse(args.next().unwrap_or("pololeti"), today)
+                .ok_or("Neznámé období, zkus `tyden`, `mesic`, `pololeti` nebo `rok`")?;
+
+            let lines = zmeny
+                .iter()
+                .filter(|zmena| {
+                    zmena.subject.to_lowercase().starts_with(&subject)
+                        && period.contains(zmena.date)
+                })
+                .map(|zmena| {
+                    format!(
+                        "{} · {} · {}",
+                        zmena.date.format("%-d.%-m."),
+                        zmena.class,
+                        zmena.line()
+                    )
+                })
+                .collect::<Vec<_>>();
+
+            // embed descriptions are capped at 4096 characters
+            let mut description = String::new();
+            for line in &lines {
+                if description.len() + line.len() > 4000 {
+                    description.push('…');
+                    break;
+                }
+                description.push_str(line);
+                description.push('\n');
+            }
+
+            Ok(embed
+                .title(format!("Změny předmětu {} {}", subject, period.label))
+                .description(if lines.is_empty() {
+                    String::from("Žádné změny v archivu")
+                } else {
+                    description
+                }))
+        }
+        _ => {
+            Err("Nechápu, co chceš z archivu 🤔 zkus `odpadle`, `suplujici` nebo `predmet`".into())
+        }
+    }
 }
 
 // slash command pro rozvrh
@@ -62,12 +639,36 @@ pub fn register() -> CreateCommand {
             )
             .required(true),
         )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::Boolean,
+            "image",
+            "pošle i screenshot z bakalářů",
+        ))
+        .add_option(
+            CreateCommandOption::new(
+                CommandOptionType::String,
+                "export",
+                "pošle změny jako soubor, bez from jen ty aktuální",
+            )
+            .add_string_choice("json", "json")
+            .add_string_choice("csv", "csv"),
+        )
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "from",
+            "exportuje archiv od data nebo za období (tyden, mesic, pololeti, rok)",
+        ))
+        .add_option(CreateCommandOption::new(
+            CommandOptionType::String,
+            "to",
+            "exportuje archiv do tohoto data",
+        ))
 }
 
 pub fn help_message() -> (&'static str, &'static str) {
     (
-        "`zmeny ~třída`",
-        "Pošle změny dané třídy.\n`~třída` musí mít velké písmeno a být bez tečky, eg. **7B**, **2A**...",
+        "`zmeny ~třída ~obrazek`",
+        "Pošle změny dané třídy.\n`~třída` musí mít velké písmeno a být bez tečky, eg. **7B**, **2A**...\n`~obrazek` je nepovinný, s ním pošle i screenshot z bakalářů\n`zmeny ~třída export ~formát ~období` pošle změny jako **json** nebo **csv** soubor, `~období` může být i datum od a do\n`zmeny archiv odpadle ~třída ~období`, `zmeny archiv suplujici ~období` a `zmeny archiv predmet ~předmět ~období` hledají ve všech změnách, které pan Špička viděl.\n`~období` je **tyden**, **mesic**, **pololeti** nebo **rok**",
     )
 }
 
@@ -76,3 +677,63 @@ pub const COMMAND: SlashCommand = SlashCommand {
     register,
     help: help_message,
 };
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = include_str!("../tests/fixtures/zmeny.aspx");
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn parses_saved_page() {
+        let zmeny = parse_zmeny(SAMPLE, date(2025, 10, 19));
+        let lines = zmeny
+            .iter()
+            .map(|zmena| (zmena.date, zmena.class.as_str(), zmena.line()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                (
+                    date(2025, 10, 20),
+                    "7B",
+                    String::from("3. hod – Fy – odpadá – 214")
+                ),
+                (
+                    date(2025, 10, 20),
+                    "7B",
+                    String::from("5. hod – M (sk1) – suplování – Novák – 101")
+                ),
+                (
+                    date(2025, 10, 20),
+                    "3A",
+                    String::from("1. hod – Čj – přesun – Dvořák – ZJ")
+                ),
+                (
+                    date(2025, 10, 21),
+                    "1C",
+                    String::from("2. hod – Bi – zrušeno")
+                ),
+            ]
+        );
+        assert!(zmeny[0].is_cancelled());
+        assert!(zmeny[1].is_substitution());
+        assert!(zmeny[3].is_cancelled());
+    }
+
+    #[test]
+    fn finds_dates() {
+        let today = date(2025, 10, 19);
+        assert_eq!(
+            find_date("Pondělí 20. 10. 2025", today),
+            Some(date(2025, 10, 20))
+        );
+        assert_eq!(find_date("Úterý 21.10.", today), Some(date(2025, 10, 21)));
+        assert_eq!(find_date("7.B 3. hod", today), None);
+        assert_eq!(find_date("Změny vyučujících", today), None);
+    }
+}